@@ -0,0 +1,208 @@
+use crate::config::Config;
+use crate::db::DbClient;
+use crate::server;
+use clap::{Parser, Subcommand};
+use std::io::BufRead;
+
+// 命令行入口，未指定子命令时启动服务
+#[derive(Debug, Parser)]
+#[command(name = "isekai-link", version, about = "IsekaiLink 订阅管理后端")]
+pub struct Cli {
+    /// 配置文件路径，优先于 ISEKAI_CONFIG 环境变量
+    #[arg(short, long, global = true)]
+    config: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 启动 HTTP 服务
+    Serve,
+    /// 用户管理
+    User {
+        #[command(subcommand)]
+        action: UserCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum UserCommand {
+    /// 创建用户
+    Add {
+        username: String,
+        /// 从标准输入读取一行作为密码，便于脚本调用
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// 修改用户密码，并注销该用户的全部会话
+    Passwd {
+        username: String,
+        /// 从标准输入读取一行作为密码，便于脚本调用
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// 删除用户及其全部链接、链接组和会话
+    Delete { username: String },
+    /// 列出全部用户
+    List,
+}
+
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let cli = Cli::parse();
+    let config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => server::main(config),
+        Command::User { action } => {
+            if let Err(msg) = run_user_command(&config, action) {
+                eprintln!("error: {msg}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn run_user_command(config: &Config, action: UserCommand) -> Result<(), String> {
+    let db_client = DbClient::connect(&config.database)
+        .await
+        .map_err(|e| format!("failed to open database: {e}"))?;
+
+    match action {
+        UserCommand::Add {
+            username,
+            password_stdin,
+        } => {
+            let password = read_password(password_stdin)?;
+            let id = db_client
+                .create_user(&username, &password)
+                .await
+                .map_err(|e| match e.as_database_error() {
+                    Some(db_err) if db_err.is_unique_violation() => {
+                        format!("user {username} already exists")
+                    }
+                    _ => format!("failed to create user: {e}"),
+                })?;
+            println!("created user {username} (id {id})");
+        }
+        UserCommand::Passwd {
+            username,
+            password_stdin,
+        } => {
+            let user = find_user(&db_client, &username).await?;
+            let password = read_password(password_stdin)?;
+            db_client
+                .update_user_password(user.id, &password)
+                .await
+                .map_err(|e| format!("failed to update password: {e}"))?;
+            let revoked = db_client
+                .delete_sessions_by_username(&username)
+                .await
+                .map_err(|e| format!("failed to revoke sessions: {e}"))?;
+            println!("password updated for {username}, {revoked} sessions revoked");
+        }
+        UserCommand::Delete { username } => {
+            let user = find_user(&db_client, &username).await?;
+            db_client
+                .delete_user(user.id)
+                .await
+                .map_err(|e| format!("failed to delete user: {e}"))?;
+            println!("deleted user {username}");
+        }
+        UserCommand::List => {
+            let users = db_client
+                .get_all_users()
+                .await
+                .map_err(|e| format!("failed to list users: {e}"))?;
+            println!("{:<6} {:<24} CREATED AT", "ID", "USERNAME");
+            for user in users {
+                println!("{:<6} {:<24} {}", user.id, user.username, user.created_at);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn find_user(db_client: &DbClient, username: &str) -> Result<crate::db::User, String> {
+    db_client
+        .get_user_by_username(username)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => format!("user {username} does not exist"),
+            e => format!("failed to query user: {e}"),
+        })
+}
+
+// 读取新密码：交互模式下需要输入两次确认
+fn read_password(from_stdin: bool) -> Result<String, String> {
+    let password = if from_stdin {
+        let mut line = String::new();
+        std::io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(|e| format!("failed to read password: {e}"))?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    } else {
+        let password = rpassword::prompt_password("Password: ")
+            .map_err(|e| format!("failed to read password: {e}"))?;
+        let confirm = rpassword::prompt_password("Confirm password: ")
+            .map_err(|e| format!("failed to read password: {e}"))?;
+        if password != confirm {
+            return Err("passwords do not match".to_string());
+        }
+        password
+    };
+
+    if password.is_empty() {
+        return Err("password must not be empty".to_string());
+    }
+    Ok(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        let cli = Cli::try_parse_from(["isekai-link"]).unwrap();
+        assert!(cli.command.is_none());
+
+        let cli = Cli::try_parse_from([
+            "isekai-link",
+            "user",
+            "add",
+            "admin",
+            "--password-stdin",
+            "-c",
+            "a.toml",
+        ])
+        .unwrap();
+        assert_eq!(cli.config.as_deref(), Some("a.toml"));
+        assert!(matches!(
+            cli.command,
+            Some(Command::User {
+                action: UserCommand::Add { ref username, password_stdin: true }
+            }) if username == "admin"
+        ));
+
+        assert!(Cli::try_parse_from(["isekai-link", "user", "passwd"]).is_err());
+    }
+}
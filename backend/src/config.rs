@@ -0,0 +1,487 @@
+use crate::proxy::output::Target;
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+// 未通过 ISEKAI_CONFIG 指定时读取的配置文件
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+// 应用配置，先读取 TOML 文件，再由 ISEKAI_* 环境变量覆盖
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub session: SessionConfig,
+    pub refresher: RefresherConfig,
+    pub fetcher: FetcherConfig,
+    pub login: LoginLimitConfig,
+    pub subscription: SubscriptionConfig,
+    pub access_log: AccessLogConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: String,              // 监听地址
+    pub trust_proxy_headers: bool, // 部署在反向代理后时，从 X-Forwarded-For / X-Real-IP 读取客户端 IP
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:30022".to_string(),
+            trust_proxy_headers: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub path: String,         // SQLite 数据库文件路径
+    pub max_connections: u32, // 连接池大小
+    pub busy_timeout_ms: u64, // 遇到锁时的等待时间（毫秒）
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: "IseKai.db".to_string(),
+            max_connections: 10,
+            busy_timeout_ms: 5000,
+        }
+    }
+}
+
+// 会话存储方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionBackend {
+    Sqlite, // 持久化到数据库，重启后会话依然有效
+    Memory, // 仅保存在内存中，适合测试
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    pub store: SessionBackend,
+    pub absolute_ttl_secs: u64,   // 会话自创建起的最长有效期（秒）
+    pub idle_ttl_secs: u64,       // 会话闲置有效期（秒）
+    pub sweep_interval_secs: u64, // 过期会话清理周期（秒）
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            store: SessionBackend::Sqlite,
+            absolute_ttl_secs: 7 * 24 * 3600,
+            idle_ttl_secs: 24 * 3600,
+            sweep_interval_secs: 300,
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn absolute_ttl(&self) -> Duration {
+        Duration::from_secs(self.absolute_ttl_secs)
+    }
+
+    pub fn idle_ttl(&self) -> Duration {
+        Duration::from_secs(self.idle_ttl_secs)
+    }
+
+    pub fn sweep_interval(&self) -> Duration {
+        Duration::from_secs(self.sweep_interval_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RefresherConfig {
+    pub concurrency: usize,      // 同时拉取上游的最大任务数
+    pub scan_interval_secs: u64, // 扫描过期缓存的周期（秒）
+}
+
+impl Default for RefresherConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            scan_interval_secs: 60,
+        }
+    }
+}
+
+impl RefresherConfig {
+    pub fn scan_interval(&self) -> Duration {
+        Duration::from_secs(self.scan_interval_secs)
+    }
+}
+
+// 上游拉取配置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FetcherConfig {
+    pub allow_internal_upstreams: bool, // 允许已保存的链接指向本机或内网地址，默认只允许公网地址
+}
+
+// 登录限流配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoginLimitConfig {
+    pub free_attempts: u32,     // 不受限制的连续失败次数
+    pub base_delay_secs: u64,   // 超出后首次等待时长，之后每次翻倍（秒）
+    pub max_delay_secs: u64,    // 单次等待时长上限（秒）
+    pub lockout_threshold: u32, // 同一用户名连续失败达到该次数后锁定账户
+    pub lockout_secs: u64,      // 账户锁定时长（秒）
+    pub reset_after_secs: u64,  // 距上次失败超过该时长后重新计数（秒）
+}
+
+impl Default for LoginLimitConfig {
+    fn default() -> Self {
+        Self {
+            free_attempts: 3,
+            base_delay_secs: 1,
+            max_delay_secs: 300,
+            lockout_threshold: 10,
+            lockout_secs: 900,
+            reset_after_secs: 3600,
+        }
+    }
+}
+
+impl LoginLimitConfig {
+    pub fn base_delay(&self) -> Duration {
+        Duration::from_secs(self.base_delay_secs)
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_secs(self.max_delay_secs)
+    }
+
+    pub fn lockout(&self) -> Duration {
+        Duration::from_secs(self.lockout_secs)
+    }
+
+    pub fn reset_after(&self) -> Duration {
+        Duration::from_secs(self.reset_after_secs)
+    }
+}
+
+// 按 User-Agent 选择订阅格式的一条规则
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserAgentRule {
+    pub keyword: String, // 不区分大小写的子串
+    pub target: Target,
+}
+
+impl UserAgentRule {
+    fn new(keyword: &str, target: Target) -> Self {
+        Self {
+            keyword: keyword.to_string(),
+            target,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubscriptionConfig {
+    pub default_target: Target,          // 没有规则匹配时的输出格式
+    pub user_agents: Vec<UserAgentRule>, // 按顺序匹配，第一条命中的规则生效
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            default_target: Target::Clash,
+            user_agents: vec![
+                UserAgentRule::new("clash", Target::Clash),
+                UserAgentRule::new("mihomo", Target::Clash),
+                UserAgentRule::new("stash", Target::Clash),
+                UserAgentRule::new("sing-box", Target::Singbox),
+                UserAgentRule::new("surge", Target::Surge),
+                UserAgentRule::new("quantumult", Target::Quanx),
+                UserAgentRule::new("v2rayn", Target::Base64),
+                UserAgentRule::new("shadowrocket", Target::Base64),
+            ],
+        }
+    }
+}
+
+impl SubscriptionConfig {
+    // 返回第一条命中的规则
+    pub fn match_user_agent(&self, user_agent: &str) -> Option<&UserAgentRule> {
+        let user_agent = user_agent.to_lowercase();
+        self.user_agents
+            .iter()
+            .find(|rule| user_agent.contains(&rule.keyword.to_lowercase()))
+    }
+}
+
+// 订阅拉取记录的保留策略，超出任一限制的记录由后台任务清理
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessLogConfig {
+    pub max_age_days: u64,       // 记录保留天数
+    pub max_rows_per_group: u32, // 每个链接组最多保留的记录数
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: 30,
+            max_rows_per_group: 1000,
+        }
+    }
+}
+
+impl AccessLogConfig {
+    pub fn max_age(&self) -> Duration {
+        Duration::from_secs(self.max_age_days * 24 * 3600)
+    }
+}
+
+// 配置加载错误
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String, std::io::Error),
+    Parse(String, toml::de::Error),
+    Env(&'static str, String), // 环境变量名与无法解析的值
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "failed to read config {path}: {e}"),
+            ConfigError::Parse(path, e) => write!(f, "failed to parse config {path}: {e}"),
+            ConfigError::Env(key, value) => write!(f, "invalid value for {key}: {value:?}"),
+            ConfigError::Invalid(msg) => write!(f, "invalid config: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    // 从指定文件或 ISEKAI_CONFIG（默认 config.toml）加载配置，默认文件不存在时使用默认值
+    pub fn load(path: Option<&str>) -> Result<Self, ConfigError> {
+        let path = path
+            .map(String::from)
+            .or_else(|| std::env::var("ISEKAI_CONFIG").ok());
+        let explicit = path.is_some();
+        let path = path.unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+
+        let mut config = if explicit || Path::new(&path).exists() {
+            Self::from_file(&path)?
+        } else {
+            Self::default()
+        };
+        config.apply_env(|key| std::env::var(key).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_string(), e))?;
+        toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_string(), e))
+    }
+
+    // 用环境变量覆盖配置项，lookup 便于在测试中替换环境
+    pub fn apply_env(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        fn parse<T: std::str::FromStr>(key: &'static str, value: String) -> Result<T, ConfigError> {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| ConfigError::Env(key, value))
+        }
+
+        if let Some(value) = lookup("ISEKAI_BIND") {
+            self.server.bind = value;
+        }
+        if let Some(value) = lookup("ISEKAI_TRUST_PROXY_HEADERS") {
+            self.server.trust_proxy_headers = parse("ISEKAI_TRUST_PROXY_HEADERS", value)?;
+        }
+        if let Some(value) = lookup("ISEKAI_DB_PATH") {
+            self.database.path = value;
+        }
+        if let Some(value) = lookup("ISEKAI_DB_MAX_CONNECTIONS") {
+            self.database.max_connections = parse("ISEKAI_DB_MAX_CONNECTIONS", value)?;
+        }
+        if let Some(value) = lookup("ISEKAI_DB_BUSY_TIMEOUT_MS") {
+            self.database.busy_timeout_ms = parse("ISEKAI_DB_BUSY_TIMEOUT_MS", value)?;
+        }
+        if let Some(value) = lookup("ISEKAI_SESSION_STORE") {
+            self.session.store = match value.trim() {
+                "sqlite" => SessionBackend::Sqlite,
+                "memory" => SessionBackend::Memory,
+                _ => return Err(ConfigError::Env("ISEKAI_SESSION_STORE", value)),
+            };
+        }
+        if let Some(value) = lookup("ISEKAI_SESSION_ABSOLUTE_TTL") {
+            self.session.absolute_ttl_secs = parse("ISEKAI_SESSION_ABSOLUTE_TTL", value)?;
+        }
+        if let Some(value) = lookup("ISEKAI_SESSION_IDLE_TTL") {
+            self.session.idle_ttl_secs = parse("ISEKAI_SESSION_IDLE_TTL", value)?;
+        }
+        if let Some(value) = lookup("ISEKAI_REFRESH_CONCURRENCY") {
+            self.refresher.concurrency = parse("ISEKAI_REFRESH_CONCURRENCY", value)?;
+        }
+        if let Some(value) = lookup("ISEKAI_REFRESH_SCAN_INTERVAL") {
+            self.refresher.scan_interval_secs = parse("ISEKAI_REFRESH_SCAN_INTERVAL", value)?;
+        }
+        if let Some(value) = lookup("ISEKAI_ALLOW_INTERNAL_UPSTREAMS") {
+            self.fetcher.allow_internal_upstreams =
+                parse("ISEKAI_ALLOW_INTERNAL_UPSTREAMS", value)?;
+        }
+        Ok(())
+    }
+
+    // 检查取值范围，避免以无效配置启动
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.database.max_connections == 0 {
+            return Err(ConfigError::Invalid(
+                "database.max_connections must be positive",
+            ));
+        }
+        if self.session.idle_ttl_secs == 0 || self.session.absolute_ttl_secs == 0 {
+            return Err(ConfigError::Invalid("session TTLs must be positive"));
+        }
+        if self.session.sweep_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "session.sweep_interval_secs must be positive",
+            ));
+        }
+        if self.refresher.concurrency == 0 {
+            return Err(ConfigError::Invalid(
+                "refresher.concurrency must be positive",
+            ));
+        }
+        if self.refresher.scan_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "refresher.scan_interval_secs must be positive",
+            ));
+        }
+        if self.login.lockout_threshold == 0 {
+            return Err(ConfigError::Invalid(
+                "login.lockout_threshold must be positive",
+            ));
+        }
+        if self
+            .subscription
+            .user_agents
+            .iter()
+            .any(|rule| rule.keyword.trim().is_empty())
+        {
+            return Err(ConfigError::Invalid(
+                "subscription.user_agents keywords must not be empty",
+            ));
+        }
+        if self.access_log.max_age_days == 0 || self.access_log.max_rows_per_group == 0 {
+            return Err(ConfigError::Invalid("access_log limits must be positive"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_partial_file_uses_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [server]
+            bind = "127.0.0.1:8080"
+
+            [refresher]
+            concurrency = 8
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.server.bind, "127.0.0.1:8080");
+        assert_eq!(config.refresher.concurrency, 8);
+        assert_eq!(config.database.path, "IseKai.db");
+        assert_eq!(config.session.store, SessionBackend::Sqlite);
+    }
+
+    #[test]
+    fn test_example_file() {
+        let config: Config = toml::from_str(include_str!("../config.example.toml")).unwrap();
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_user_agent_rules() {
+        let subscription = SubscriptionConfig::default();
+        let target = |ua: &str| subscription.match_user_agent(ua).map(|rule| rule.target);
+        assert_eq!(
+            target("ClashMetaForAndroid/2.11.1.Meta"),
+            Some(Target::Clash)
+        );
+        assert_eq!(target("Stash/2.4.7 Clash/1.9.0"), Some(Target::Clash));
+        assert_eq!(
+            target("SFA/1.11.4 (sing-box 1.11.4)"),
+            Some(Target::Singbox)
+        );
+        assert_eq!(target("v2rayN/7.4.2"), Some(Target::Base64));
+        assert_eq!(target("curl/8.5.0"), None);
+
+        // 配置文件中的规则整体替换默认规则
+        let config: Config = toml::from_str(
+            r#"
+            [subscription]
+            default_target = "base64"
+            user_agents = [{ keyword = "curl", target = "singbox" }]
+            "#,
+        )
+        .unwrap();
+        let rule = config.subscription.match_user_agent("curl/8.5.0").unwrap();
+        assert_eq!(rule.target, Target::Singbox);
+        assert!(config.subscription.match_user_agent("clash.meta").is_none());
+        assert_eq!(config.subscription.default_target, Target::Base64);
+        assert!(toml::from_str::<Config>("[subscription]\ndefault_target = \"v2\"").is_err());
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        assert!(toml::from_str::<Config>("[database]\nfile = \"a.db\"").is_err());
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("ISEKAI_DB_PATH", "/data/isekai.db"),
+            ("ISEKAI_SESSION_STORE", "memory"),
+            ("ISEKAI_SESSION_IDLE_TTL", "600"),
+            ("ISEKAI_ALLOW_INTERNAL_UPSTREAMS", "true"),
+        ]);
+        let mut config = Config::default();
+        config
+            .apply_env(|key| env.get(key).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.database.path, "/data/isekai.db");
+        assert_eq!(config.session.store, SessionBackend::Memory);
+        assert_eq!(config.session.idle_ttl(), Duration::from_secs(600));
+        assert!(config.fetcher.allow_internal_upstreams);
+        assert_eq!(config.server.bind, "0.0.0.0:30022");
+
+        let mut config = Config::default();
+        let err = config
+            .apply_env(|key| (key == "ISEKAI_REFRESH_CONCURRENCY").then(|| "many".to_string()))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Env("ISEKAI_REFRESH_CONCURRENCY", _)
+        ));
+    }
+}
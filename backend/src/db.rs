@@ -1,10 +1,16 @@
-use sqlx::sqlite::SqlitePoolOptions;
+use crate::config::DatabaseConfig;
+use crate::proxy::dedup::DedupPolicy;
+use crate::proxy::rename::RenameRule;
+use crate::utils::password;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnection, SqlitePoolOptions};
+use sqlx::types::Json;
 use sqlx::{
     Error,
     sqlite::{SqliteConnectOptions, SqlitePool},
 };
 
-const CREATE_TABLES_SQL: &str = include_str!("sql/create_tables.sql");
+mod migrations;
 
 #[derive(Clone)]
 pub struct DbClient {
@@ -13,17 +19,15 @@ pub struct DbClient {
 
 impl DbClient {
     /// 创建数据库连接池并初始化表结构
-    pub async fn connect() -> Result<Self, Error> {
-        let database_url = "IseKai.db".to_string();
-
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self, Error> {
         let options = SqliteConnectOptions::new()
-            .filename(database_url)
-            .pragma("busy_timeout", "5000") // 遇到锁时等待 5 秒
+            .filename(&config.path)
+            .pragma("busy_timeout", config.busy_timeout_ms.to_string()) // 遇到锁时的等待时间
             .pragma("journal_mode", "WAL") // 启用 WAL 模式提升读并发
             .create_if_missing(true);
 
         let pool = SqlitePoolOptions::new()
-            .max_connections(10)
+            .max_connections(config.max_connections)
             .connect_with(options)
             .await?;
 
@@ -37,22 +41,31 @@ impl DbClient {
         Ok(Self { pool })
     }
 
-    /// 初始化数据库表结构
-    async fn init(pool: &SqlitePool) -> Result<(), Error> {
-        sqlx::query(CREATE_TABLES_SQL)
-            .execute(pool)
-            .await
-            .expect("Failed to initialize database tables");
+    /// 连接一个只存在于内存中的独立数据库，不与其他测试共享数据
+    #[cfg(test)]
+    pub async fn connect_in_memory() -> Result<Self, Error> {
+        // 内存数据库属于单个连接，连接池只能保留一个连接
+        Self::connect(&DatabaseConfig {
+            path: ":memory:".to_string(),
+            max_connections: 1,
+            ..Default::default()
+        })
+        .await
+    }
 
-        Ok(())
+    /// 执行数据库迁移，将表结构升级到最新版本
+    async fn init(pool: &SqlitePool) -> Result<(), Error> {
+        migrations::run(pool).await
     }
 
     /// 获取数据库连接池引用
+    #[allow(dead_code)]
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
 
     /// 执行原始SQL查询
+    #[allow(dead_code)]
     pub async fn execute_raw(&self, sql: &str) -> Result<u64, Error> {
         let result = sqlx::query(sql)
             .execute(&self.pool)
@@ -63,7 +76,9 @@ impl DbClient {
     }
 
     // ============== users 表操作 ==============
-    pub async fn create_user(&self, username: &str, pwd_hash: &str) -> Result<i64, Error> {
+    /// 创建用户，密码以 Argon2id 哈希后存储
+    pub async fn create_user(&self, username: &str, password: &str) -> Result<i64, Error> {
+        let pwd_hash = password::hash_password_blocking(password).await;
         let sql = "INSERT INTO users (username, pwd_hash) VALUES (?, ?)";
         let result = sqlx::query(sql)
             .bind(username)
@@ -73,6 +88,7 @@ impl DbClient {
         Ok(result.last_insert_rowid())
     }
 
+    #[allow(dead_code)]
     pub async fn get_user_by_id(&self, id: i64) -> Result<User, Error> {
         let sql = "SELECT id, username, pwd_hash, created_at FROM users WHERE id = ?";
         sqlx::query_as::<_, User>(sql)
@@ -89,7 +105,14 @@ impl DbClient {
             .await
     }
 
-    pub async fn update_user_password(&self, id: i64, new_pwd_hash: &str) -> Result<bool, Error> {
+    pub async fn get_all_users(&self) -> Result<Vec<User>, Error> {
+        let sql = "SELECT id, username, pwd_hash, created_at FROM users ORDER BY id";
+        sqlx::query_as::<_, User>(sql).fetch_all(&self.pool).await
+    }
+
+    /// 修改用户密码，新密码以 Argon2id 哈希后存储
+    pub async fn update_user_password(&self, id: i64, new_password: &str) -> Result<bool, Error> {
+        let new_pwd_hash = password::hash_password_blocking(new_password).await;
         let sql = "UPDATE users SET pwd_hash = ? WHERE id = ?";
         let result = sqlx::query(sql)
             .bind(new_pwd_hash)
@@ -106,6 +129,8 @@ impl DbClient {
     }
 
     // ============== link_groups 表操作 ==============
+    /// 创建链接组，基础信息与节点处理规则在同一事务中写入
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_link_group(
         &self,
         user_id: i64,
@@ -116,7 +141,10 @@ impl DbClient {
         is_public: bool,
         cache_content: Option<&str>,
         cache_refresh_interval: i32,
+        rules: &GroupRules,
     ) -> Result<i64, Error> {
+        let mut tx = self.pool.begin().await?;
+
         let sql = "INSERT INTO link_groups (user_id, name, slug, key, description, is_public, cache_content, cache_refresh_interval)
                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
         let result = sqlx::query(sql)
@@ -128,14 +156,19 @@ impl DbClient {
             .bind(is_public)
             .bind(cache_content)
             .bind(cache_refresh_interval)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
-        Ok(result.last_insert_rowid())
+        let id = result.last_insert_rowid();
+        Self::write_group_rules(&mut tx, id, rules).await?;
+
+        tx.commit().await?;
+        Ok(id)
     }
 
     pub async fn get_link_group_by_id(&self, id: i64) -> Result<LinkGroup, Error> {
         let sql = "SELECT id, user_id, name, slug, key, description, is_public, cache_content,
-                          cache_refresh_interval, cache_updated_at, created_at
+                          cache_refresh_interval, cache_updated_at, created_at,
+                          node_include, node_exclude, rename_rules, dedup_policy, template
                    FROM link_groups WHERE id = ?";
         sqlx::query_as::<_, LinkGroup>(sql)
             .bind(id)
@@ -145,7 +178,8 @@ impl DbClient {
 
     pub async fn get_groups_by_user(&self, user_id: i64) -> Result<Vec<LinkGroup>, Error> {
         let sql = "SELECT id, user_id, name, slug, key, description, is_public, cache_content,
-                          cache_refresh_interval, cache_updated_at, created_at
+                          cache_refresh_interval, cache_updated_at, created_at,
+                          node_include, node_exclude, rename_rules, dedup_policy, template
                    FROM link_groups WHERE user_id = ?";
         sqlx::query_as::<_, LinkGroup>(sql)
             .bind(user_id)
@@ -155,7 +189,8 @@ impl DbClient {
 
     pub async fn get_group_by_slug(&self, slug: &str) -> Result<LinkGroup, Error> {
         let sql = "SELECT id, user_id, name, slug, key, description, is_public, cache_content,
-                          cache_refresh_interval, cache_updated_at, created_at
+                          cache_refresh_interval, cache_updated_at, created_at,
+                          node_include, node_exclude, rename_rules, dedup_policy, template
                    FROM link_groups WHERE slug = ?";
         sqlx::query_as::<_, LinkGroup>(sql)
             .bind(slug)
@@ -163,33 +198,43 @@ impl DbClient {
             .await
     }
 
+    /// 更新属于指定用户的链接组及其节点处理规则，保留已生成的缓存
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_link_group(
         &self,
         id: i64,
+        user_id: i64,
         name: &str,
         slug: &str,
         key: Option<&str>,
         description: Option<&str>,
         is_public: bool,
-        cache_content: Option<&str>,
         cache_refresh_interval: i32,
+        rules: &GroupRules,
     ) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+
         let sql = "UPDATE link_groups
-                  SET name = ?, slug = ?, key = ?, description = ?, is_public = ?,
-                      cache_content = ?, cache_refresh_interval = ?, cache_updated_at = CURRENT_TIMESTAMP
-                  WHERE id = ?";
+                  SET name = ?, slug = ?, key = ?, description = ?, is_public = ?, cache_refresh_interval = ?
+                  WHERE id = ? AND user_id = ?";
         let result = sqlx::query(sql)
             .bind(name)
             .bind(slug)
             .bind(key)
             .bind(description)
             .bind(is_public)
-            .bind(cache_content)
             .bind(cache_refresh_interval)
             .bind(id)
-            .execute(&self.pool)
+            .bind(user_id)
+            .execute(&mut *tx)
             .await?;
-        Ok(result.rows_affected() > 0)
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        Self::write_group_rules(&mut tx, id, rules).await?;
+
+        tx.commit().await?;
+        Ok(true)
     }
 
     pub async fn update_group_cache(&self, id: i64, cache_content: &str) -> Result<bool, Error> {
@@ -204,13 +249,49 @@ impl DbClient {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn delete_link_group(&self, id: i64) -> Result<bool, Error> {
-        let sql = "DELETE FROM link_groups WHERE id = ?";
-        let result = sqlx::query(sql).bind(id).execute(&self.pool).await?;
+    /// 记录链接组生成失败的时间，旧缓存保持不变
+    pub async fn mark_group_refresh_failed(&self, id: i64) -> Result<(), Error> {
+        sqlx::query("UPDATE link_groups SET cache_attempted_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // 写入链接组的节点处理规则，由创建与更新在各自的事务中调用
+    async fn write_group_rules(
+        tx: &mut SqliteConnection,
+        id: i64,
+        rules: &GroupRules,
+    ) -> Result<(), Error> {
+        let sql = "UPDATE link_groups
+                  SET node_include = ?, node_exclude = ?, rename_rules = ?, dedup_policy = ?, template = ?
+                  WHERE id = ?";
+        sqlx::query(sql)
+            .bind(rules.node_include.as_deref())
+            .bind(rules.node_exclude.as_deref())
+            .bind(&rules.rename_rules)
+            .bind(rules.dedup_policy)
+            .bind(rules.template.as_deref())
+            .bind(id)
+            .execute(tx)
+            .await?;
+        Ok(())
+    }
+
+    /// 删除属于指定用户的链接组
+    pub async fn delete_link_group(&self, id: i64, user_id: i64) -> Result<bool, Error> {
+        let sql = "DELETE FROM link_groups WHERE id = ? AND user_id = ?";
+        let result = sqlx::query(sql)
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
         Ok(result.rows_affected() > 0)
     }
 
     // ============== links 表操作 ==============
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_link(
         &self,
         user_id: i64,
@@ -242,7 +323,7 @@ impl DbClient {
 
     pub async fn get_link_by_id(&self, id: i64) -> Result<Link, Error> {
         let sql = "SELECT id, user_id, type, is_public, name, slug, description, content,
-                          cache_content, cache_refresh_interval, cache_updated_at, created_at
+                          cache_content, cache_userinfo, cache_refresh_interval, cache_updated_at, created_at
                    FROM links WHERE id = ?";
         sqlx::query_as::<_, Link>(sql)
             .bind(id)
@@ -250,9 +331,10 @@ impl DbClient {
             .await
     }
 
+    #[allow(dead_code)]
     pub async fn get_link_by_slug(&self, slug: &str) -> Result<Link, Error> {
         let sql = "SELECT id, user_id, type, is_public, name, slug, description, content,
-                          cache_content, cache_refresh_interval, cache_updated_at, created_at
+                          cache_content, cache_userinfo, cache_refresh_interval, cache_updated_at, created_at
                    FROM links WHERE slug = ?";
         sqlx::query_as::<_, Link>(sql)
             .bind(slug)
@@ -262,7 +344,7 @@ impl DbClient {
 
     pub async fn get_links_by_user(&self, user_id: i64) -> Result<Vec<Link>, Error> {
         let sql = "SELECT id, user_id, type, is_public, name, slug, description, content,
-                          cache_content, cache_refresh_interval, cache_updated_at, created_at
+                          cache_content, cache_userinfo, cache_refresh_interval, cache_updated_at, created_at
                    FROM links WHERE user_id = ?";
         sqlx::query_as::<_, Link>(sql)
             .bind(user_id)
@@ -270,22 +352,28 @@ impl DbClient {
             .await
     }
 
+    /// 更新属于指定用户的链接，上游地址或类型变化时清空缓存及流量信息
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_link(
         &self,
         id: i64,
+        user_id: i64,
         type_: &str,
         is_public: bool,
         name: Option<&str>,
         slug: Option<&str>,
         description: Option<&str>,
         content: &str,
-        cache_content: Option<&str>,
         cache_refresh_interval: i32,
     ) -> Result<bool, Error> {
+        // SET 中引用的列均为更新前的值
         let sql = "UPDATE links
                   SET type = ?, is_public = ?, name = ?, slug = ?, description = ?, content = ?,
-                      cache_content = ?, cache_refresh_interval = ?, cache_updated_at = CURRENT_TIMESTAMP
-                  WHERE id = ?";
+                      cache_content = CASE WHEN content = ? AND type = ? THEN cache_content ELSE NULL END,
+                      cache_userinfo = CASE WHEN content = ? AND type = ? THEN cache_userinfo ELSE NULL END,
+                      cache_refresh_interval = ?, cache_updated_at = CURRENT_TIMESTAMP,
+                      cache_attempted_at = NULL
+                  WHERE id = ? AND user_id = ?";
         let result = sqlx::query(sql)
             .bind(type_)
             .bind(is_public)
@@ -293,43 +381,391 @@ impl DbClient {
             .bind(slug)
             .bind(description)
             .bind(content)
-            .bind(cache_content)
+            .bind(content)
+            .bind(type_)
+            .bind(content)
+            .bind(type_)
             .bind(cache_refresh_interval)
             .bind(id)
+            .bind(user_id)
             .execute(&self.pool)
             .await?;
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn update_link_cache(&self, id: i64, cache_content: &str) -> Result<bool, Error> {
+    /// 写入拉取结果及上游返回的 Subscription-Userinfo
+    pub async fn update_link_cache(
+        &self,
+        id: i64,
+        cache_content: &str,
+        cache_userinfo: Option<&str>,
+    ) -> Result<bool, Error> {
         let sql = "UPDATE links
-                  SET cache_content = ?, cache_updated_at = CURRENT_TIMESTAMP
+                  SET cache_content = ?, cache_userinfo = ?, cache_updated_at = CURRENT_TIMESTAMP
                   WHERE id = ?";
         let result = sqlx::query(sql)
             .bind(cache_content)
+            .bind(cache_userinfo)
             .bind(id)
             .execute(&self.pool)
             .await?;
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn delete_link(&self, id: i64) -> Result<bool, Error> {
-        let sql = "DELETE FROM links WHERE id = ?";
-        let result = sqlx::query(sql).bind(id).execute(&self.pool).await?;
+    /// 记录链接拉取失败的时间，旧缓存保持不变
+    pub async fn mark_link_refresh_failed(&self, id: i64) -> Result<(), Error> {
+        sqlx::query("UPDATE links SET cache_attempted_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 删除属于指定用户的链接
+    pub async fn delete_link(&self, id: i64, user_id: i64) -> Result<bool, Error> {
+        let sql = "DELETE FROM links WHERE id = ? AND user_id = ?";
+        let result = sqlx::query(sql)
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ============== group_links 表操作 ==============
+    /// 将链接加入链接组，追加到末尾；链接组与链接须同属指定用户，已在组内或不属于该用户时返回 false
+    pub async fn add_link_to_group(
+        &self,
+        group_id: i64,
+        link_id: i64,
+        user_id: i64,
+    ) -> Result<bool, Error> {
+        let sql = "INSERT OR IGNORE INTO group_links (group_id, link_id, position)
+                   SELECT g.id, l.id, (SELECT COALESCE(MAX(position) + 1, 0) FROM group_links WHERE group_id = g.id)
+                   FROM link_groups g, links l
+                   WHERE g.id = ? AND l.id = ? AND g.user_id = ? AND l.user_id = ?";
+        let result = sqlx::query(sql)
+            .bind(group_id)
+            .bind(link_id)
+            .bind(user_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 将链接移出属于指定用户的链接组
+    pub async fn remove_link_from_group(
+        &self,
+        group_id: i64,
+        link_id: i64,
+        user_id: i64,
+    ) -> Result<bool, Error> {
+        let sql = "DELETE FROM group_links
+                   WHERE group_id = ? AND link_id = ?
+                     AND group_id IN (SELECT id FROM link_groups WHERE user_id = ?)";
+        let result = sqlx::query(sql)
+            .bind(group_id)
+            .bind(link_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 设置链接在属于指定用户的链接组内的优先级
+    pub async fn set_group_link_priority(
+        &self,
+        group_id: i64,
+        link_id: i64,
+        user_id: i64,
+        priority: i32,
+    ) -> Result<bool, Error> {
+        let sql = "UPDATE group_links SET priority = ?
+                   WHERE group_id = ? AND link_id = ?
+                     AND group_id IN (SELECT id FROM link_groups WHERE user_id = ?)";
+        let result = sqlx::query(sql)
+            .bind(priority)
+            .bind(group_id)
+            .bind(link_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 按组内顺序获取链接组的成员及其优先级
+    pub async fn get_group_members(&self, group_id: i64) -> Result<Vec<GroupMember>, Error> {
+        let sql = "SELECT link_id, position, priority FROM group_links
+                   WHERE group_id = ?
+                   ORDER BY position, link_id";
+        sqlx::query_as::<_, GroupMember>(sql)
+            .bind(group_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// 按给定顺序重排组内链接，link_ids 必须恰好是组内的全部链接，且链接组属于 user_id
+    pub async fn reorder_group_links(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        link_ids: &[i64],
+    ) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let sql = "SELECT COUNT(*) FROM group_links gl
+                   JOIN link_groups g ON g.id = gl.group_id
+                   WHERE gl.group_id = ? AND g.user_id = ?";
+        let count: i64 = sqlx::query_scalar(sql)
+            .bind(group_id)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if count == 0 || count != link_ids.len() as i64 {
+            return Ok(false);
+        }
+
+        let sql = "UPDATE group_links SET position = ? WHERE group_id = ? AND link_id = ?";
+        for (position, link_id) in link_ids.iter().enumerate() {
+            let result = sqlx::query(sql)
+                .bind(position as i64)
+                .bind(group_id)
+                .bind(link_id)
+                .execute(&mut *tx)
+                .await?;
+            if result.rows_affected() == 0 {
+                // 未提交的事务在 drop 时自动回滚
+                return Ok(false);
+            }
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// 按组内顺序获取链接组包含的链接
+    pub async fn get_links_by_group(&self, group_id: i64) -> Result<Vec<Link>, Error> {
+        let sql = "SELECT l.id, l.user_id, l.type, l.is_public, l.name, l.slug, l.description, l.content,
+                          l.cache_content, l.cache_userinfo, l.cache_refresh_interval, l.cache_updated_at, l.created_at
+                   FROM group_links gl
+                   JOIN links l ON l.id = gl.link_id
+                   WHERE gl.group_id = ?
+                   ORDER BY gl.position, gl.link_id";
+        sqlx::query_as::<_, Link>(sql)
+            .bind(group_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// 获取链接组内各链接缓存的流量信息
+    pub async fn get_group_userinfos(&self, group_id: i64) -> Result<Vec<String>, Error> {
+        let sql = "SELECT l.cache_userinfo
+                   FROM group_links gl
+                   JOIN links l ON l.id = gl.link_id
+                   WHERE gl.group_id = ? AND l.cache_userinfo IS NOT NULL";
+        sqlx::query_scalar(sql)
+            .bind(group_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// 获取包含指定链接的所有链接组 id
+    pub async fn get_group_ids_by_link(&self, link_id: i64) -> Result<Vec<i64>, Error> {
+        let sql = "SELECT group_id FROM group_links WHERE link_id = ?";
+        sqlx::query_scalar(sql)
+            .bind(link_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    // ============== sessions 表操作 ==============
+    pub async fn create_session(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        now: i64,
+    ) -> Result<(), Error> {
+        let sql = "INSERT INTO sessions (token_hash, user_id, created_at, last_seen_at) VALUES (?, ?, ?, ?)";
+        sqlx::query(sql)
+            .bind(token_hash)
+            .bind(user_id)
+            .bind(now)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_session(&self, token_hash: &str) -> Result<Option<Session>, Error> {
+        let sql = "SELECT u.username, s.created_at, s.last_seen_at
+                   FROM sessions s
+                   JOIN users u ON u.id = s.user_id
+                   WHERE s.token_hash = ?";
+        sqlx::query_as::<_, Session>(sql)
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn touch_session(&self, token_hash: &str, now: i64) -> Result<bool, Error> {
+        let sql = "UPDATE sessions SET last_seen_at = ? WHERE token_hash = ?";
+        let result = sqlx::query(sql)
+            .bind(now)
+            .bind(token_hash)
+            .execute(&self.pool)
+            .await?;
         Ok(result.rows_affected() > 0)
     }
 
+    pub async fn delete_session(&self, token_hash: &str) -> Result<bool, Error> {
+        let sql = "DELETE FROM sessions WHERE token_hash = ?";
+        let result = sqlx::query(sql)
+            .bind(token_hash)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_sessions_by_username(&self, username: &str) -> Result<u64, Error> {
+        let sql = "DELETE FROM sessions WHERE user_id = (SELECT id FROM users WHERE username = ?)";
+        let result = sqlx::query(sql).bind(username).execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    /// 删除创建时间早于 created_before 或最近访问早于 seen_before 的会话
+    pub async fn delete_expired_sessions(
+        &self,
+        created_before: i64,
+        seen_before: i64,
+    ) -> Result<u64, Error> {
+        let sql = "DELETE FROM sessions WHERE created_at <= ? OR last_seen_at <= ?";
+        let result = sqlx::query(sql)
+            .bind(created_before)
+            .bind(seen_before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    // ============== group_access_logs 表操作 ==============
+    pub async fn create_group_access_log(
+        &self,
+        group_id: i64,
+        client_ip: &str,
+        user_agent: Option<&str>,
+        key_used: Option<&str>,
+        status: u16,
+    ) -> Result<i64, Error> {
+        let sql =
+            "INSERT INTO group_access_logs (group_id, client_ip, user_agent, key_used, status)
+                   VALUES (?, ?, ?, ?, ?)";
+        let result = sqlx::query(sql)
+            .bind(group_id)
+            .bind(client_ip)
+            .bind(user_agent)
+            .bind(key_used)
+            .bind(status)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// 按时间倒序分页获取链接组的拉取记录
+    pub async fn get_group_access_logs(
+        &self,
+        group_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<GroupAccessLog>, Error> {
+        let sql = "SELECT id, group_id, accessed_at, client_ip, user_agent, key_used, status
+                   FROM group_access_logs WHERE group_id = ?
+                   ORDER BY id DESC LIMIT ? OFFSET ?";
+        sqlx::query_as::<_, GroupAccessLog>(sql)
+            .bind(group_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn count_group_access_logs(&self, group_id: i64) -> Result<i64, Error> {
+        let sql = "SELECT COUNT(*) FROM group_access_logs WHERE group_id = ?";
+        sqlx::query_scalar(sql)
+            .bind(group_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// 删除早于 max_age_secs 秒的拉取记录，并让每个链接组只保留最新的 max_rows_per_group 条
+    pub async fn delete_old_access_logs(
+        &self,
+        max_age_secs: u64,
+        max_rows_per_group: u32,
+    ) -> Result<u64, Error> {
+        let by_age = "DELETE FROM group_access_logs WHERE accessed_at < datetime('now', ?)";
+        let expired = sqlx::query(by_age)
+            .bind(format!("-{max_age_secs} seconds"))
+            .execute(&self.pool)
+            .await?;
+        let by_count = "DELETE FROM group_access_logs WHERE id IN (
+                            SELECT id FROM (
+                                SELECT id, ROW_NUMBER() OVER (PARTITION BY group_id ORDER BY id DESC) AS row_num
+                                FROM group_access_logs
+                            ) WHERE row_num > ?
+                        )";
+        let overflow = sqlx::query(by_count)
+            .bind(max_rows_per_group)
+            .execute(&self.pool)
+            .await?;
+        Ok(expired.rows_affected() + overflow.rows_affected())
+    }
+
     // ============== 高级查询操作 ==============
+    /// 获取缓存已过期、需要重新拉取的链接（刷新间隔为 0 表示从不刷新），
+    /// 拉取失败的链接在一个刷新间隔内不再重试
+    pub async fn get_stale_links(&self) -> Result<Vec<Link>, Error> {
+        let sql = "SELECT id, user_id, type, is_public, name, slug, description, content,
+                          cache_content, cache_userinfo, cache_refresh_interval, cache_updated_at, created_at
+                   FROM links
+                   WHERE cache_refresh_interval > 0
+                     AND (cache_content IS NULL
+                          OR cache_updated_at <= datetime('now', '-' || cache_refresh_interval || ' seconds'))
+                     AND (cache_attempted_at IS NULL
+                          OR cache_attempted_at <= datetime('now', '-' || cache_refresh_interval || ' seconds'))";
+        sqlx::query_as::<_, Link>(sql).fetch_all(&self.pool).await
+    }
+
+    /// 获取缓存已过期、需要重新生成的链接组（刷新间隔为 0 表示从不刷新），
+    /// 生成失败的链接组在一个刷新间隔内不再重试
+    pub async fn get_stale_groups(&self) -> Result<Vec<LinkGroup>, Error> {
+        let sql = "SELECT id, user_id, name, slug, key, description, is_public, cache_content,
+                          cache_refresh_interval, cache_updated_at, created_at,
+                          node_include, node_exclude, rename_rules, dedup_policy, template
+                   FROM link_groups
+                   WHERE cache_refresh_interval > 0
+                     AND (cache_content IS NULL
+                          OR cache_updated_at <= datetime('now', '-' || cache_refresh_interval || ' seconds'))
+                     AND (cache_attempted_at IS NULL
+                          OR cache_attempted_at <= datetime('now', '-' || cache_refresh_interval || ' seconds'))";
+        sqlx::query_as::<_, LinkGroup>(sql)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    #[allow(dead_code)]
     pub async fn get_public_links(&self) -> Result<Vec<Link>, Error> {
         let sql = "SELECT id, user_id, type, is_public, name, slug, description, content,
-                          cache_content, cache_refresh_interval, cache_updated_at, created_at
+                          cache_content, cache_userinfo, cache_refresh_interval, cache_updated_at, created_at
                    FROM links WHERE is_public = true";
         sqlx::query_as::<_, Link>(sql).fetch_all(&self.pool).await
     }
 
+    #[allow(dead_code)]
     pub async fn get_public_groups(&self) -> Result<Vec<LinkGroup>, Error> {
         let sql = "SELECT id, user_id, name, slug, key, description, is_public, cache_content,
-                          cache_refresh_interval, cache_updated_at, created_at
+                          cache_refresh_interval, cache_updated_at, created_at,
+                          node_include, node_exclude, rename_rules, dedup_policy, template
                    FROM link_groups WHERE is_public = true";
         sqlx::query_as::<_, LinkGroup>(sql)
             .fetch_all(&self.pool)
@@ -338,6 +774,7 @@ impl DbClient {
 }
 
 // 定义返回类型的结构体
+#[allow(dead_code)]
 #[derive(Debug, sqlx::FromRow)]
 pub struct User {
     pub id: i64,
@@ -347,6 +784,13 @@ pub struct User {
 }
 
 #[derive(Debug, sqlx::FromRow)]
+pub struct Session {
+    pub username: String,
+    pub created_at: i64,
+    pub last_seen_at: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct LinkGroup {
     pub id: i64,
     pub user_id: i64,
@@ -359,13 +803,46 @@ pub struct LinkGroup {
     pub cache_refresh_interval: i32,
     pub cache_updated_at: String,
     pub created_at: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub rules: GroupRules,
 }
 
-#[derive(Debug, sqlx::FromRow)]
+// 链接组生成配置时对节点的处理规则，依次执行过滤、重命名与去重，最后注入模板
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(default)]
+pub struct GroupRules {
+    pub node_include: Option<String>, // 保留规则，每行一个正则，节点名称须匹配其中之一
+    pub node_exclude: Option<String>, // 排除规则，每行一个正则，匹配任意一条的节点被丢弃
+    pub rename_rules: Json<Vec<RenameRule>>, // 重命名规则，按顺序执行
+    pub dedup_policy: DedupPolicy,    // 跨链接的重复节点处理策略
+    pub template: Option<String>,     // Clash 配置模板，节点注入其中
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct GroupMember {
+    pub link_id: i64,
+    pub position: i64,
+    pub priority: i32,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct GroupAccessLog {
+    pub id: i64,
+    pub group_id: i64,
+    pub accessed_at: String,
+    pub client_ip: String,
+    pub user_agent: Option<String>,
+    pub key_used: Option<String>,
+    pub status: u16,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct Link {
     pub id: i64,
     pub user_id: i64,
     #[sqlx(rename = "type")]
+    #[serde(rename = "type")]
     pub type_: String,
     pub is_public: bool,
     pub name: Option<String>,
@@ -373,6 +850,7 @@ pub struct Link {
     pub description: Option<String>,
     pub content: String,
     pub cache_content: Option<String>,
+    pub cache_userinfo: Option<String>,
     pub cache_refresh_interval: i32,
     pub cache_updated_at: String,
     pub created_at: String,
@@ -384,7 +862,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_user_operations() {
-        let db = DbClient::connect().await.unwrap();
+        let db = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
 
         // 创建用户
         let user_id = db.create_user("testuser", "hash123").await.unwrap();
@@ -397,6 +875,7 @@ mod tests {
         // 通过用户名获取用户
         let user_by_name = db.get_user_by_username("testuser").await.unwrap();
         assert_eq!(user_by_name.id, user_id);
+        assert!(password::verify_password("hash123", &user_by_name.pwd_hash).is_valid());
 
         // 更新密码
         let updated = db
@@ -404,6 +883,11 @@ mod tests {
             .await
             .unwrap();
         assert!(updated);
+        let user = db.get_user_by_id(user_id).await.unwrap();
+        assert_eq!(
+            password::verify_password("newhash456", &user.pwd_hash),
+            password::PasswordCheck::Valid
+        );
 
         // 删除用户
         let deleted = db.delete_user(user_id).await.unwrap();
@@ -412,7 +896,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_link_group_operations() {
-        let db = DbClient::connect().await.unwrap();
+        let db = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
 
         // 创建用户
         let user_id = db.create_user("testuser2", "hash123").await.unwrap();
@@ -428,6 +912,7 @@ mod tests {
                 true,
                 Some("cached content"),
                 3600,
+                &GroupRules::default(),
             )
             .await
             .unwrap();
@@ -442,23 +927,253 @@ mod tests {
         let updated = db
             .update_link_group(
                 group_id,
+                user_id,
                 "Updated Group",
                 "updated-group",
                 Some("new-key"),
                 Some("Updated description"),
                 false,
-                Some("updated cache"),
                 7200,
+                &GroupRules::default(),
             )
             .await
             .unwrap();
         assert!(updated);
 
+        // 其他用户无法修改或删除
+        let other_id = db.create_user("testuser2-other", "hash123").await.unwrap();
+        let rules = GroupRules::default();
+        let updated = db
+            .update_link_group(
+                group_id, other_id, "Stolen", "stolen", None, None, false, 0, &rules,
+            )
+            .await
+            .unwrap();
+        assert!(!updated);
+        assert!(!db.delete_link_group(group_id, other_id).await.unwrap());
+        assert_eq!(
+            db.get_link_group_by_id(group_id).await.unwrap().name,
+            "Updated Group"
+        );
+        db.delete_user(other_id).await.unwrap();
+
         // 删除链接组
-        let deleted = db.delete_link_group(group_id).await.unwrap();
+        let deleted = db.delete_link_group(group_id, user_id).await.unwrap();
         assert!(deleted);
 
         // 删除用户
         db.delete_user(user_id).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_stale_links() {
+        let db = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+        let user_id = db.create_user("testuser3", "hash123").await.unwrap();
+
+        // 刷新间隔为 0 的链接从不刷新
+        let never = db
+            .create_link(
+                user_id, "clash", false, None, None, None, "http://a", None, 0,
+            )
+            .await
+            .unwrap();
+        // 没有缓存的链接需要立即拉取
+        let empty = db
+            .create_link(
+                user_id, "clash", false, None, None, None, "http://b", None, 3600,
+            )
+            .await
+            .unwrap();
+        // 刚写入缓存的链接尚未过期
+        let fresh = db
+            .create_link(
+                user_id,
+                "clash",
+                false,
+                None,
+                None,
+                None,
+                "http://c",
+                Some("cached"),
+                3600,
+            )
+            .await
+            .unwrap();
+
+        let stale: Vec<i64> = db
+            .get_stale_links()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|link| link.user_id == user_id)
+            .map(|link| link.id)
+            .collect();
+        assert!(!stale.contains(&never));
+        assert!(stale.contains(&empty));
+        assert!(!stale.contains(&fresh));
+
+        db.delete_user(user_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_link_scoped_to_owner() {
+        let db = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+        let user_id = db.create_user("testuser5", "hash123").await.unwrap();
+        let other_id = db.create_user("testuser6", "hash123").await.unwrap();
+        let link_id = db
+            .create_link(
+                user_id, "clash", false, None, None, None, "http://a", None, 0,
+            )
+            .await
+            .unwrap();
+        db.update_link_cache(link_id, "cached", Some("upload=1"))
+            .await
+            .unwrap();
+
+        // 其他用户的更新与删除不影响任何行
+        let update = |owner, content| {
+            db.update_link(link_id, owner, "clash", false, None, None, None, content, 0)
+        };
+        assert!(!update(other_id, "http://b").await.unwrap());
+        assert!(!db.delete_link(link_id, other_id).await.unwrap());
+
+        // 上游不变时保留缓存，变化时一并清空流量信息
+        assert!(update(user_id, "http://a").await.unwrap());
+        let link = db.get_link_by_id(link_id).await.unwrap();
+        assert_eq!(link.cache_content.as_deref(), Some("cached"));
+        assert_eq!(link.cache_userinfo.as_deref(), Some("upload=1"));
+        assert!(update(user_id, "http://b").await.unwrap());
+        let link = db.get_link_by_id(link_id).await.unwrap();
+        assert_eq!(link.content, "http://b");
+        assert!(link.cache_content.is_none());
+        assert!(link.cache_userinfo.is_none());
+
+        assert!(db.delete_link(link_id, user_id).await.unwrap());
+        db.delete_user(user_id).await.unwrap();
+        db.delete_user(other_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_group_link_operations() {
+        let db = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+        let user_id = db.create_user("testuser4", "hash123").await.unwrap();
+
+        let group_id = db
+            .create_link_group(
+                user_id,
+                "Members",
+                "members-group",
+                None,
+                None,
+                false,
+                None,
+                0,
+                &GroupRules::default(),
+            )
+            .await
+            .unwrap();
+        let first = db
+            .create_link(
+                user_id, "clash", false, None, None, None, "http://a", None, 0,
+            )
+            .await
+            .unwrap();
+        let second = db
+            .create_link(
+                user_id, "clash", false, None, None, None, "http://b", None, 0,
+            )
+            .await
+            .unwrap();
+
+        // 加入链接组，重复加入不生效
+        assert!(
+            db.add_link_to_group(group_id, first, user_id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            db.add_link_to_group(group_id, second, user_id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !db.add_link_to_group(group_id, first, user_id)
+                .await
+                .unwrap()
+        );
+
+        // 其他用户既不能把自己的链接加入该组，也不能修改组内成员
+        let other_id = db.create_user("testuser4-other", "hash123").await.unwrap();
+        let foreign = db
+            .create_link(
+                other_id, "clash", false, None, None, None, "http://c", None, 0,
+            )
+            .await
+            .unwrap();
+        assert!(
+            !db.add_link_to_group(group_id, foreign, other_id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !db.add_link_to_group(group_id, foreign, user_id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !db.set_group_link_priority(group_id, first, other_id, 5)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !db.remove_link_from_group(group_id, first, other_id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !db.reorder_group_links(group_id, other_id, &[second, first])
+                .await
+                .unwrap()
+        );
+        db.delete_user(other_id).await.unwrap();
+        let ids: Vec<i64> = db
+            .get_links_by_group(group_id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|link| link.id)
+            .collect();
+        assert_eq!(ids, vec![first, second]);
+
+        // 重排顺序，不完整的列表会被拒绝
+        assert!(
+            !db.reorder_group_links(group_id, user_id, &[second])
+                .await
+                .unwrap()
+        );
+        assert!(
+            db.reorder_group_links(group_id, user_id, &[second, first])
+                .await
+                .unwrap()
+        );
+        let ids: Vec<i64> = db
+            .get_links_by_group(group_id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|link| link.id)
+            .collect();
+        assert_eq!(ids, vec![second, first]);
+
+        // 移除链接，删除链接时成员关系级联删除
+        assert!(
+            db.remove_link_from_group(group_id, second, user_id)
+                .await
+                .unwrap()
+        );
+        db.delete_link(first, user_id).await.unwrap();
+        assert!(db.get_links_by_group(group_id).await.unwrap().is_empty());
+
+        db.delete_user(user_id).await.unwrap();
+    }
 }
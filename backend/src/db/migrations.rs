@@ -0,0 +1,162 @@
+use sqlx::{Error, sqlite::SqlitePool};
+
+// 单个数据库迁移，版本号必须递增且发布后不可修改
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+// 按版本号排列的全部迁移
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "init",
+        sql: include_str!("../sql/migrations/0001_init.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "group_access_logs",
+        sql: include_str!("../sql/migrations/0002_group_access_logs.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "link_userinfo",
+        sql: include_str!("../sql/migrations/0003_link_userinfo.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "group_node_filters",
+        sql: include_str!("../sql/migrations/0004_group_node_filters.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "group_rename_rules",
+        sql: include_str!("../sql/migrations/0005_group_rename_rules.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "group_dedup",
+        sql: include_str!("../sql/migrations/0006_group_dedup.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "group_template",
+        sql: include_str!("../sql/migrations/0007_group_template.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "access_log_retention",
+        sql: include_str!("../sql/migrations/0008_access_log_retention.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "refresh_attempts",
+        sql: include_str!("../sql/migrations/0009_refresh_attempts.sql"),
+    },
+];
+
+const CREATE_MIGRATIONS_TABLE_SQL: &str = "CREATE TABLE IF NOT EXISTS schema_migrations (
+    version INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+)";
+
+// 当前程序支持的最新数据库版本
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+// 读取数据库当前版本，未执行过迁移时为 0
+pub async fn current_version(pool: &SqlitePool) -> Result<i64, Error> {
+    sqlx::query(CREATE_MIGRATIONS_TABLE_SQL)
+        .execute(pool)
+        .await?;
+    let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM schema_migrations")
+        .fetch_one(pool)
+        .await?;
+    Ok(version.unwrap_or(0))
+}
+
+// 依次执行尚未应用的迁移，每个迁移在独立事务中执行
+// 数据库版本高于程序支持的版本时拒绝启动，避免旧程序破坏新结构
+pub async fn run(pool: &SqlitePool) -> Result<(), Error> {
+    let current = current_version(pool).await?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(Error::Configuration(
+            format!("database schema version {current} is newer than supported version {latest}")
+                .into(),
+        ));
+    }
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current)
+    {
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_migrations (version, name) VALUES (?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        log::info!(
+            "applied migration {:04}_{}",
+            migration.version,
+            migration.name
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn memory_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_versions_are_increasing() {
+        assert!(
+            MIGRATIONS
+                .windows(2)
+                .all(|pair| pair[0].version < pair[1].version)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_is_idempotent() {
+        let pool = memory_pool().await;
+        run(&pool).await.unwrap();
+        run(&pool).await.unwrap();
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM schema_migrations")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(applied, MIGRATIONS.len() as i64);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_newer_database() {
+        let pool = memory_pool().await;
+        run(&pool).await.unwrap();
+        sqlx::query("INSERT INTO schema_migrations (version, name) VALUES (?, 'future')")
+            .bind(latest_version() + 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(run(&pool).await.is_err());
+    }
+}
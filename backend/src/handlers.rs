@@ -1 +1,57 @@
-pub mod login;
\ No newline at end of file
+pub mod convert;
+pub mod groups;
+pub mod links;
+pub mod login;
+pub mod subscription;
+
+use crate::db::DbClient;
+use crate::types::api_response::BizCode;
+use axum::http::HeaderMap;
+use std::net::{IpAddr, SocketAddr};
+
+// 根据鉴权中间件写入的用户名查询用户 id
+pub(crate) async fn resolve_user_id(db_client: &DbClient, username: &str) -> Result<i64, BizCode> {
+    match db_client.get_user_by_username(username).await {
+        Ok(user) => Ok(user.id),
+        // 会话有效但用户已被删除
+        Err(sqlx::Error::RowNotFound) => Err(BizCode::Unauthorized),
+        Err(e) => Err(db_error_code(&e)),
+    }
+}
+
+// 将数据库错误映射为业务码
+pub(crate) fn db_error_code(e: &sqlx::Error) -> BizCode {
+    match e {
+        sqlx::Error::RowNotFound => BizCode::NotFound,
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => BizCode::Conflict,
+        _ => {
+            log::error!("database error: {e}");
+            BizCode::ServerError
+        }
+    }
+}
+
+// 获取客户端 IP，部署在反向代理后并开启 trust_proxy_headers 时优先读取代理头
+pub(crate) fn client_ip(
+    headers: &HeaderMap,
+    peer: SocketAddr,
+    trust_proxy_headers: bool,
+) -> IpAddr {
+    if trust_proxy_headers {
+        let forwarded = headers
+            .get("X-Forwarded-For")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        let real_ip = || {
+            headers
+                .get("X-Real-IP")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|ip| ip.trim().parse().ok())
+        };
+        if let Some(ip) = forwarded.or_else(real_ip) {
+            return ip;
+        }
+    }
+    peer.ip()
+}
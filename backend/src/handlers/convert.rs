@@ -0,0 +1,218 @@
+use crate::db::GroupRules;
+use crate::handlers::resolve_user_id;
+use crate::proxy::filter::NodeFilter;
+use crate::proxy::output::{self, Target};
+use crate::services::fetcher::FetchError;
+use crate::types::api_response::*;
+use crate::types::app_state::AppState;
+use axum::{
+    Extension,
+    extract::{Query, State},
+    http::{HeaderMap, HeaderValue, header},
+    response::{IntoResponse, Response},
+};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Deserialize;
+use std::sync::Arc;
+use url::Url;
+
+#[derive(Deserialize)]
+pub struct ConvertQuery {
+    url: String,
+    #[serde(rename = "type")]
+    type_: Option<String>, // 上游内容类型，clash 或 uri，默认 clash
+    target: Option<String>,  // 输出格式，默认 clash
+    include: Option<String>, // 与链接组相同的过滤规则，每行一个正则
+    exclude: Option<String>,
+}
+
+// 转换临时的上游订阅地址，结果作为文件下载，不保存任何内容
+pub async fn convert(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Query(query): Query<ConvertQuery>,
+) -> Response {
+    if let Err(code) = resolve_user_id(&state.db_client, &username).await {
+        return ApiResponse::<()>::error(code, None).into_response();
+    }
+
+    let url = match Url::parse(&query.url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        _ => {
+            return ApiResponse::<()>::error(
+                BizCode::BadRequest,
+                Some("仅支持 http 或 https 地址"),
+            )
+            .into_response();
+        }
+    };
+    let target = match query.target.as_deref() {
+        None | Some("") => Target::Clash,
+        Some(value) => match Target::parse(value) {
+            Some(target) => target,
+            None => {
+                return ApiResponse::<()>::error(BizCode::BadRequest, Some("不支持的输出格式"))
+                    .into_response();
+            }
+        },
+    };
+    let link_type = match query.type_.as_deref() {
+        None | Some("") => "clash",
+        Some(value @ ("clash" | "uri")) => value,
+        Some(_) => {
+            return ApiResponse::<()>::error(BizCode::BadRequest, Some("不支持的链接类型"))
+                .into_response();
+        }
+    };
+    if let Err(e) = NodeFilter::new(query.include.as_deref(), query.exclude.as_deref()) {
+        let msg = format!("过滤规则不是有效的正则表达式：{e}");
+        return ApiResponse::<()>::error(BizCode::BadRequest, Some(&msg)).into_response();
+    }
+    let rules = GroupRules {
+        node_include: query.include,
+        node_exclude: query.exclude,
+        ..Default::default()
+    };
+
+    let converted = match state
+        .group_builder
+        .convert(url.as_str(), link_type, &rules)
+        .await
+    {
+        Ok(converted) => converted,
+        Err(e) => {
+            log::warn!("failed to fetch {url} for conversion: {e}");
+            let msg = match e {
+                FetchError::Forbidden(_) => "不允许访问内网地址",
+                FetchError::TooLarge => "上游内容过大",
+                FetchError::Request(_) => "拉取上游订阅失败",
+            };
+            return ApiResponse::<()>::error(BizCode::BadRequest, Some(msg)).into_response();
+        }
+    };
+    if converted.total == 0 {
+        return ApiResponse::<()>::error(BizCode::BadRequest, Some("上游内容中没有可识别的节点"))
+            .into_response();
+    }
+    log::info!(
+        "{username} converted {url} to {target:?}: {} of {} nodes kept",
+        converted.kept,
+        converted.total
+    );
+
+    let rendered = match output::render(target, &converted.content) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            log::error!("failed to render conversion of {url}: {e}");
+            return ApiResponse::<()>::error(BizCode::ServerError, None).into_response();
+        }
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(target.content_type()),
+    );
+    // 以上游域名作为文件名
+    let name = format!(
+        "{}.{}",
+        url.host_str().unwrap_or("config"),
+        target.extension()
+    );
+    let filename = utf8_percent_encode(&name, NON_ALPHANUMERIC);
+    if let Ok(value) = HeaderValue::from_str(&format!("attachment; filename*=UTF-8''{filename}")) {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    (headers, rendered.content).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::db::DbClient;
+    use crate::services::fetcher::Fetcher;
+    use axum::{body, http::StatusCode};
+
+    const UPSTREAM: &str = r#"
+proxies:
+  - { name: "HK 01", type: ss, server: a.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+  - { name: "JP 01", type: ss, server: b.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+"#;
+
+    fn query(url: &str, target: Option<&str>) -> ConvertQuery {
+        ConvertQuery {
+            url: url.to_string(),
+            type_: None,
+            target: target.map(String::from),
+            include: None,
+            exclude: Some("^JP".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_convert() {
+        let db_client = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+        let user_id = db_client.create_user("convert-user", "pw").await.unwrap();
+        // 上游由固定内容的拉取器提供，不发起网络请求
+        let state =
+            AppState::for_test_with_fetcher(db_client.clone(), Fetcher::Static(UPSTREAM.into()));
+        let user = || Extension("convert-user".to_string());
+        let url = "http://upstream.example/sub";
+
+        let resp = convert(
+            State(state.clone()),
+            user(),
+            Query(query(url, Some("singbox"))),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(
+            resp.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename*=UTF-8''upstream%2Eexample%2Ejson"
+        );
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let config: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let tags: Vec<&str> = config["outbounds"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|outbound| outbound["type"] == "shadowsocks")
+            .map(|outbound| outbound["tag"].as_str().unwrap())
+            .collect();
+        assert_eq!(tags, vec!["HK 01"]);
+
+        let resp = convert(
+            State(state.clone()),
+            user(),
+            Query(query(url, Some("base64"))),
+        )
+        .await;
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let nodes =
+            crate::proxy::uri::parse_subscription(std::str::from_utf8(&body).unwrap()).nodes;
+        assert_eq!(nodes.len(), 1);
+
+        // 非 http 地址、内网地址与未知格式被拒绝
+        for url in [
+            "file:///etc/passwd",
+            "http://127.0.0.1/sub",
+            "http://[fd00::1]/sub",
+        ] {
+            let resp = convert(State(state.clone()), user(), Query(query(url, None))).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{url}");
+        }
+        let resp = convert(State(state.clone()), user(), Query(query(url, Some("v2")))).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        db_client.delete_user(user_id).await.unwrap();
+    }
+}
@@ -0,0 +1,948 @@
+use crate::db::{GroupAccessLog, GroupMember, GroupRules, LinkGroup};
+use crate::handlers::{db_error_code, resolve_user_id};
+use crate::proxy::filter::NodeFilter;
+use crate::proxy::rename::Renamer;
+use crate::proxy::template;
+use crate::services::group_builder::GroupPreview;
+use crate::types::api_response::*;
+use crate::types::app_state::AppState;
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct GroupRequest {
+    name: String,
+    slug: String,        // 对外订阅地址中的唯一标识
+    key: Option<String>, // 访问密钥，为空时无需密钥
+    description: Option<String>,
+    #[serde(default)]
+    is_public: bool,
+    #[serde(default)]
+    cache_refresh_interval: i32, // 缓存刷新间隔（秒），0 表示从不刷新
+    #[serde(flatten)]
+    rules: GroupRules, // 节点处理规则与配置模板
+}
+
+impl GroupRequest {
+    // 校验请求参数，返回错误信息
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("名称不能为空".into());
+        }
+        let slug_valid = !self.slug.is_empty()
+            && self
+                .slug
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !slug_valid {
+            return Err("slug 只能包含字母、数字、- 和 _".into());
+        }
+        if self.cache_refresh_interval < 0 {
+            return Err("刷新间隔不能为负数".into());
+        }
+        let rules = &self.rules;
+        if NodeFilter::new(rules.node_include.as_deref(), rules.node_exclude.as_deref()).is_err() {
+            return Err("节点过滤规则不是有效的正则表达式".into());
+        }
+        if Renamer::new(&rules.rename_rules).is_err() {
+            return Err("重命名规则不是有效的正则表达式".into());
+        }
+        if let Some(template) = rules.template.as_deref().filter(|t| !t.trim().is_empty()) {
+            template::parse(template).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+// 获取当前用户的全部链接组
+pub async fn list_groups(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+) -> ApiResponse<Vec<LinkGroup>> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+
+    match state.db_client.get_groups_by_user(user_id).await {
+        Ok(groups) => ApiResponse::success(groups),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 创建链接组
+pub async fn create_group(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Json(req): Json<GroupRequest>,
+) -> ApiResponse<LinkGroup> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+    if let Err(msg) = req.validate() {
+        return ApiResponse::error(BizCode::BadRequest, Some(&msg));
+    }
+
+    let created = state
+        .db_client
+        .create_link_group(
+            user_id,
+            &req.name,
+            &req.slug,
+            req.key.as_deref(),
+            req.description.as_deref(),
+            req.is_public,
+            None,
+            req.cache_refresh_interval,
+            &req.rules,
+        )
+        .await;
+    let id = match created {
+        Ok(id) => id,
+        Err(e) => return group_error(&e),
+    };
+    rebuild_group(&state, id).await;
+
+    match state.db_client.get_link_group_by_id(id).await {
+        Ok(group) => ApiResponse::success(group),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 获取单个链接组
+pub async fn get_group(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+) -> ApiResponse<LinkGroup> {
+    match owned_group(&state, &username, id).await {
+        Ok(group) => ApiResponse::success(group),
+        Err(code) => ApiResponse::error(code, None),
+    }
+}
+
+// 更新链接组，保留已生成的缓存内容
+pub async fn update_group(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+    Json(req): Json<GroupRequest>,
+) -> ApiResponse<LinkGroup> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+    if let Err(msg) = req.validate() {
+        return ApiResponse::error(BizCode::BadRequest, Some(&msg));
+    }
+
+    let updated = state
+        .db_client
+        .update_link_group(
+            id,
+            user_id,
+            &req.name,
+            &req.slug,
+            req.key.as_deref(),
+            req.description.as_deref(),
+            req.is_public,
+            req.cache_refresh_interval,
+            &req.rules,
+        )
+        .await;
+    match updated {
+        Ok(true) => {}
+        Ok(false) => return ApiResponse::error(BizCode::NotFound, None),
+        Err(e) => return group_error(&e),
+    }
+    rebuild_group(&state, id).await;
+
+    match state.db_client.get_link_group_by_id(id).await {
+        Ok(group) => ApiResponse::success(group),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 删除链接组
+pub async fn delete_group(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+) -> ApiResponse<()> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+
+    match state.db_client.delete_link_group(id, user_id).await {
+        Ok(true) => ApiResponse::success_empty(),
+        Ok(false) => ApiResponse::error(BizCode::NotFound, None),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 拉取记录分页参数
+#[derive(Deserialize)]
+pub struct PageQuery {
+    #[serde(default = "default_page")]
+    page: i64, // 页码，从 1 开始
+    #[serde(default = "default_page_size")]
+    page_size: i64, // 每页条数，最大 200
+}
+
+fn default_page() -> i64 {
+    1
+}
+
+fn default_page_size() -> i64 {
+    50
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccessLogPage {
+    items: Vec<GroupAccessLog>,
+    total: i64,
+    page: i64,
+    page_size: i64,
+}
+
+// 分页获取链接组的订阅拉取记录，最新的在前
+pub async fn get_access_log(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+    Query(query): Query<PageQuery>,
+) -> ApiResponse<AccessLogPage> {
+    if let Err(code) = owned_group(&state, &username, id).await {
+        return ApiResponse::error(code, None);
+    }
+    if query.page < 1 || !(1..=200).contains(&query.page_size) {
+        return ApiResponse::error(BizCode::BadRequest, Some("分页参数错误"));
+    }
+
+    let offset = (query.page - 1).saturating_mul(query.page_size);
+    let items = match state
+        .db_client
+        .get_group_access_logs(id, query.page_size, offset)
+        .await
+    {
+        Ok(items) => items,
+        Err(e) => return ApiResponse::error(db_error_code(&e), None),
+    };
+    let total = match state.db_client.count_group_access_logs(id).await {
+        Ok(total) => total,
+        Err(e) => return ApiResponse::error(db_error_code(&e), None),
+    };
+
+    ApiResponse::success(AccessLogPage {
+        items,
+        total,
+        page: query.page,
+        page_size: query.page_size,
+    })
+}
+
+// 获取链接组的成员链接及其优先级
+pub async fn list_group_links(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+) -> ApiResponse<Vec<GroupMember>> {
+    if let Err(code) = owned_group(&state, &username, id).await {
+        return ApiResponse::error(code, None);
+    }
+
+    match state.db_client.get_group_members(id).await {
+        Ok(members) => ApiResponse::success(members),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GroupLinkRequest {
+    #[serde(default)]
+    priority: i32, // 去重时数值大的链接优先保留
+}
+
+// 将链接加入链接组并设置优先级，已在组内时只更新优先级
+pub async fn put_group_link(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path((id, link_id)): Path<(i64, i64)>,
+    Json(req): Json<GroupLinkRequest>,
+) -> ApiResponse<()> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+
+    // 链接组或链接不属于当前用户时不会写入成员关系，随后的优先级更新也不会命中
+    if let Err(e) = state
+        .db_client
+        .add_link_to_group(id, link_id, user_id)
+        .await
+    {
+        return ApiResponse::error(db_error_code(&e), None);
+    }
+    match state
+        .db_client
+        .set_group_link_priority(id, link_id, user_id, req.priority)
+        .await
+    {
+        Ok(true) => {
+            rebuild_group(&state, id).await;
+            ApiResponse::success_empty()
+        }
+        Ok(false) => ApiResponse::error(BizCode::NotFound, None),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 将链接移出链接组
+pub async fn delete_group_link(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path((id, link_id)): Path<(i64, i64)>,
+) -> ApiResponse<()> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+
+    match state
+        .db_client
+        .remove_link_from_group(id, link_id, user_id)
+        .await
+    {
+        Ok(true) => {
+            rebuild_group(&state, id).await;
+            ApiResponse::success_empty()
+        }
+        Ok(false) => ApiResponse::error(BizCode::NotFound, None),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReorderRequest {
+    link_ids: Vec<i64>, // 组内全部链接的新顺序
+}
+
+// 按给定顺序重排组内链接，列表必须恰好包含组内的全部链接
+pub async fn reorder_group_links(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+    Json(req): Json<ReorderRequest>,
+) -> ApiResponse<()> {
+    let group = match owned_group(&state, &username, id).await {
+        Ok(group) => group,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+
+    match state
+        .db_client
+        .reorder_group_links(id, group.user_id, &req.link_ids)
+        .await
+    {
+        Ok(true) => {
+            rebuild_group(&state, id).await;
+            ApiResponse::success_empty()
+        }
+        Ok(false) => ApiResponse::error(
+            BizCode::BadRequest,
+            Some("链接列表必须恰好包含组内的全部链接"),
+        ),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 预览链接组的生成结果：按当前规则列出保留、丢弃与合并的节点
+pub async fn preview_group(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+) -> ApiResponse<GroupPreview> {
+    let group = match owned_group(&state, &username, id).await {
+        Ok(group) => group,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+
+    match state.group_builder.preview(&group).await {
+        Ok(preview) => ApiResponse::success(preview),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 读取属于当前用户的链接组，其他用户的链接组按不存在处理
+pub(crate) async fn owned_group(
+    state: &AppState,
+    username: &str,
+    id: i64,
+) -> Result<LinkGroup, BizCode> {
+    let user_id = resolve_user_id(&state.db_client, username).await?;
+    match state.db_client.get_link_group_by_id(id).await {
+        Ok(group) if group.user_id == user_id => Ok(group),
+        Ok(_) => Err(BizCode::NotFound),
+        Err(e) => Err(db_error_code(&e)),
+    }
+}
+
+// 链接组或其成员变化后立即重新生成缓存，避免订阅继续返回旧内容；失败时保留旧缓存
+pub(crate) async fn rebuild_group(state: &AppState, id: i64) {
+    if let Err(e) = state.group_builder.rebuild(id).await {
+        log::error!("failed to rebuild group {id}: {e}");
+    }
+}
+
+fn group_error<T>(e: &sqlx::Error) -> ApiResponse<T> {
+    match db_error_code(e) {
+        BizCode::Conflict => ApiResponse::error(BizCode::Conflict, Some("slug 已被占用")),
+        code => ApiResponse::error(code, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::db::DbClient;
+    use crate::proxy::dedup::DedupPolicy;
+    use crate::proxy::filter::FilterVerdict;
+    use crate::proxy::output::Target;
+    use crate::proxy::rename::RenameRule;
+
+    fn group_request(slug: &str) -> GroupRequest {
+        GroupRequest {
+            name: "Team".to_string(),
+            slug: slug.to_string(),
+            key: Some("secret".to_string()),
+            description: None,
+            is_public: true,
+            cache_refresh_interval: 3600,
+            rules: GroupRules::default(),
+        }
+    }
+
+    const UPSTREAM: &str = r#"
+proxies:
+  - { name: "剩余流量：10 GB", type: ss, server: a.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+  - { name: "香港 01", type: ss, server: b.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+  - { name: "美国 01", type: ss, server: c.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+"#;
+
+    // 另一个上游以不同名称提供了同一节点
+    const MIRROR: &str = r#"
+proxies:
+  - { name: "HK Mirror", type: ss, server: b.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+"#;
+
+    // 预览测试的公共数据：创建用户，并以给定规则创建包含各上游的链接组，上游按顺序附带优先级
+    struct Fixture {
+        state: Arc<AppState>,
+        user_id: i64,
+        username: String,
+        group: LinkGroup,
+    }
+
+    impl Fixture {
+        async fn new(username: &str, rules: GroupRules, upstreams: &[(&str, i32)]) -> Self {
+            let db_client = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+            let user_id = db_client.create_user(username, "pw").await.unwrap();
+            let state = AppState::for_test(db_client.clone());
+
+            let mut req = group_request(username);
+            req.rules = rules;
+            let group = create_group(
+                State(state.clone()),
+                Extension(username.to_string()),
+                Json(req),
+            )
+            .await
+            .data
+            .unwrap();
+            for (content, priority) in upstreams {
+                let link_id = db_client
+                    .create_link(
+                        user_id,
+                        "clash",
+                        false,
+                        None,
+                        None,
+                        None,
+                        "http://a",
+                        Some(content),
+                        0,
+                    )
+                    .await
+                    .unwrap();
+                let resp = put_group_link(
+                    State(state.clone()),
+                    Extension(username.to_string()),
+                    Path((group.id, link_id)),
+                    Json(GroupLinkRequest {
+                        priority: *priority,
+                    }),
+                )
+                .await;
+                assert_eq!(resp.code, BizCode::Success.code());
+            }
+
+            Self {
+                state,
+                user_id,
+                username: username.to_string(),
+                group,
+            }
+        }
+
+        fn owner(&self) -> Extension<String> {
+            Extension(self.username.clone())
+        }
+
+        async fn preview(&self) -> GroupPreview {
+            preview_group(State(self.state.clone()), self.owner(), Path(self.group.id))
+                .await
+                .data
+                .unwrap()
+        }
+
+        async fn update(&self, rules: GroupRules) -> ApiResponse<LinkGroup> {
+            let mut req = group_request(&self.username);
+            req.rules = rules;
+            update_group(
+                State(self.state.clone()),
+                self.owner(),
+                Path(self.group.id),
+                Json(req),
+            )
+            .await
+        }
+
+        async fn cleanup(self) {
+            self.state
+                .db_client
+                .delete_user(self.user_id)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_group_crud_and_conflict() {
+        let db_client = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+        let owner_id = db_client.create_user("groups-owner", "pw").await.unwrap();
+        let other_id = db_client.create_user("groups-other", "pw").await.unwrap();
+        let state = AppState::for_test(db_client.clone());
+        let owner = || Extension("groups-owner".to_string());
+        let other = || Extension("groups-other".to_string());
+
+        let created = create_group(
+            State(state.clone()),
+            owner(),
+            Json(group_request("groups-test")),
+        )
+        .await
+        .data
+        .unwrap();
+        assert_eq!(created.user_id, owner_id);
+
+        // 重复的 slug 返回冲突
+        let resp = create_group(
+            State(state.clone()),
+            other(),
+            Json(group_request("groups-test")),
+        )
+        .await;
+        assert_eq!(resp.code, BizCode::Conflict.code());
+
+        // 非法 slug 被拒绝
+        let resp = create_group(State(state.clone()), owner(), Json(group_request("a/b"))).await;
+        assert_eq!(resp.code, BizCode::BadRequest.code());
+
+        // 其他用户无法访问
+        let resp = get_group(State(state.clone()), other(), Path(created.id)).await;
+        assert_eq!(resp.code, BizCode::NotFound.code());
+        let resp = update_group(
+            State(state.clone()),
+            other(),
+            Path(created.id),
+            Json(group_request("groups-stolen")),
+        )
+        .await;
+        assert_eq!(resp.code, BizCode::NotFound.code());
+        let resp = delete_group(State(state.clone()), other(), Path(created.id)).await;
+        assert_eq!(resp.code, BizCode::NotFound.code());
+        let foreign = db_client
+            .create_link(
+                other_id, "clash", false, None, None, None, "http://a", None, 0,
+            )
+            .await
+            .unwrap();
+        let resp = put_group_link(
+            State(state.clone()),
+            owner(),
+            Path((created.id, foreign)),
+            Json(GroupLinkRequest { priority: 0 }),
+        )
+        .await;
+        assert_eq!(resp.code, BizCode::NotFound.code());
+
+        // 拉取记录分页，且只有所有者可以查看
+        for status in [200, 403, 200] {
+            db_client
+                .create_group_access_log(
+                    created.id,
+                    "10.0.0.1",
+                    Some("clash.meta"),
+                    Some("secret"),
+                    status,
+                )
+                .await
+                .unwrap();
+        }
+        let page = |page, page_size| Query(PageQuery { page, page_size });
+        let logs = get_access_log(State(state.clone()), owner(), Path(created.id), page(1, 2))
+            .await
+            .data
+            .unwrap();
+        assert_eq!(logs.total, 3);
+        assert_eq!(logs.items.len(), 2);
+        assert_eq!(logs.items[0].status, 200);
+        assert_eq!(logs.items[1].status, 403);
+        let logs = get_access_log(State(state.clone()), owner(), Path(created.id), page(2, 2))
+            .await
+            .data
+            .unwrap();
+        assert_eq!(logs.items.len(), 1);
+        let resp =
+            get_access_log(State(state.clone()), other(), Path(created.id), page(1, 2)).await;
+        assert_eq!(resp.code, BizCode::NotFound.code());
+        let resp =
+            get_access_log(State(state.clone()), owner(), Path(created.id), page(0, 2)).await;
+        assert_eq!(resp.code, BizCode::BadRequest.code());
+
+        let updated = update_group(
+            State(state.clone()),
+            owner(),
+            Path(created.id),
+            Json(group_request("groups-test-2")),
+        )
+        .await
+        .data
+        .unwrap();
+        assert_eq!(updated.slug, "groups-test-2");
+        let resp = delete_group(State(state.clone()), owner(), Path(created.id)).await;
+        assert_eq!(resp.code, BizCode::Success.code());
+
+        db_client.delete_user(owner_id).await.unwrap();
+        db_client.delete_user(other_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_preview_applies_filters() {
+        let rules = GroupRules {
+            node_include: Some("香港|流量".to_string()),
+            node_exclude: Some("剩余流量".to_string()),
+            ..Default::default()
+        };
+        let fixture = Fixture::new("preview-filters", rules, &[(UPSTREAM, 0)]).await;
+
+        let preview = fixture.preview().await;
+        let kept: Vec<&str> = preview.kept.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(kept, vec!["香港 01"]);
+        let dropped: Vec<(&str, &FilterVerdict)> = preview
+            .dropped
+            .iter()
+            .map(|node| (node.name.as_str(), &node.verdict))
+            .collect();
+        assert_eq!(
+            dropped,
+            vec![
+                (
+                    "剩余流量：10 GB",
+                    &FilterVerdict::Excluded("剩余流量".to_string())
+                ),
+                ("美国 01", &FilterVerdict::NotIncluded),
+            ]
+        );
+
+        fixture.cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn test_preview_renames_nodes() {
+        let rules = GroupRules {
+            node_include: Some("香港".to_string()),
+            rename_rules: vec![RenameRule::Region].into(),
+            ..Default::default()
+        };
+        let fixture = Fixture::new("preview-rename", rules, &[(UPSTREAM, 0)]).await;
+
+        let preview = fixture.preview().await;
+        let kept: Vec<(&str, &str)> = preview
+            .kept
+            .iter()
+            .map(|node| (node.original_name.as_str(), node.name.as_str()))
+            .collect();
+        assert_eq!(kept, vec![("香港 01", "🇭🇰 HK 01")]);
+
+        fixture.cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn test_preview_dedups_by_priority() {
+        let rules = GroupRules {
+            node_include: Some("香港|HK".to_string()),
+            dedup_policy: DedupPolicy::KeepPriority,
+            ..Default::default()
+        };
+        let fixture = Fixture::new("preview-dedup", rules, &[(UPSTREAM, 0), (MIRROR, 10)]).await;
+
+        let members = list_group_links(
+            State(fixture.state.clone()),
+            fixture.owner(),
+            Path(fixture.group.id),
+        )
+        .await
+        .data
+        .unwrap();
+        assert_eq!(
+            members.iter().map(|m| m.priority).collect::<Vec<_>>(),
+            vec![0, 10]
+        );
+
+        // 优先级高的镜像保留，另一个上游的同一节点被合并
+        let preview = fixture.preview().await;
+        let kept: Vec<&str> = preview.kept.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(kept, vec!["HK Mirror"]);
+        assert_eq!(preview.duplicates.len(), 1);
+        assert_eq!(preview.duplicates[0].dropped[0].name, "香港 01");
+
+        fixture.cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn test_preview_warns_about_unsupported_targets() {
+        let upstream = r#"
+proxies:
+  - { name: "SS", type: ss, server: a.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+  - { name: "HY2", type: hysteria2, server: b.example.com, port: 443, password: pw }
+"#;
+        let fixture =
+            Fixture::new("preview-warnings", GroupRules::default(), &[(upstream, 0)]).await;
+
+        // Shadowsocks 节点所有格式都能表示，Hysteria2 无法输出到 Quantumult X
+        let preview = fixture.preview().await;
+        let warnings: Vec<(&str, Target)> = preview
+            .warnings
+            .iter()
+            .map(|warning| (warning.name.as_str(), warning.target))
+            .collect();
+        assert_eq!(warnings, vec![("HY2", Target::Quanx)]);
+
+        fixture.cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn test_invalid_rules_are_rejected() {
+        let fixture = Fixture::new("rules-invalid", GroupRules::default(), &[]).await;
+
+        let resp = fixture
+            .update(GroupRules {
+                node_exclude: Some("(".to_string()),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(resp.code, BizCode::BadRequest.code());
+
+        // 模板中的未知占位符被拒绝
+        let resp = fixture
+            .update(GroupRules {
+                template: Some(
+                    "proxy-groups:\n  - { name: P, type: select, proxies: [$nodes] }".to_string(),
+                ),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(resp.code, BizCode::BadRequest.code());
+
+        fixture.cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn test_template_receives_nodes() {
+        let rules = GroupRules {
+            template: Some(
+                "proxy-groups:\n  - { name: P, type: select, proxies: [$all] }".to_string(),
+            ),
+            ..Default::default()
+        };
+        let fixture = Fixture::new("template-nodes", rules, &[(UPSTREAM, 0), (MIRROR, 0)]).await;
+
+        let content = fixture
+            .state
+            .group_builder
+            .build(fixture.group.id)
+            .await
+            .unwrap()
+            .unwrap();
+        let config: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(
+            config["proxy-groups"][0]["proxies"]
+                .as_sequence()
+                .unwrap()
+                .len(),
+            4
+        );
+
+        fixture.cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn test_reorder_group_links() {
+        let fixture = Fixture::new(
+            "reorder-links",
+            GroupRules::default(),
+            &[(UPSTREAM, 0), (MIRROR, 0)],
+        )
+        .await;
+        let state = || State(fixture.state.clone());
+        let id = fixture.group.id;
+        let members = || async {
+            let members = list_group_links(state(), fixture.owner(), Path(id))
+                .await
+                .data
+                .unwrap();
+            members.iter().map(|m| m.link_id).collect::<Vec<_>>()
+        };
+        let reorder = |username: &str, link_ids: Vec<i64>| {
+            reorder_group_links(
+                state(),
+                Extension(username.to_string()),
+                Path(id),
+                Json(ReorderRequest { link_ids }),
+            )
+        };
+
+        let other_id = fixture
+            .state
+            .db_client
+            .create_user("reorder-links-other", "pw")
+            .await
+            .unwrap();
+        let ids = members().await;
+        let reversed: Vec<i64> = ids.iter().rev().copied().collect();
+
+        // 不完整的列表与其他用户的请求被拒绝
+        let resp = reorder(&fixture.username, vec![ids[0]]).await;
+        assert_eq!(resp.code, BizCode::BadRequest.code());
+        let resp = reorder("reorder-links-other", reversed.clone()).await;
+        assert_eq!(resp.code, BizCode::NotFound.code());
+        assert_eq!(members().await, ids);
+
+        // 重排后缓存按新顺序重新生成
+        let resp = reorder(&fixture.username, reversed.clone()).await;
+        assert_eq!(resp.code, BizCode::Success.code());
+        assert_eq!(members().await, reversed);
+        let group = fixture
+            .state
+            .db_client
+            .get_link_group_by_id(id)
+            .await
+            .unwrap();
+        let config: serde_yaml::Value =
+            serde_yaml::from_str(&group.cache_content.unwrap()).unwrap();
+        assert_eq!(config["proxies"][0]["name"], "HK Mirror");
+
+        fixture.state.db_client.delete_user(other_id).await.unwrap();
+        fixture.cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn test_sub_reflects_rule_changes() {
+        use crate::handlers::subscription::{SubQuery, get_group_sub};
+        use axum::extract::ConnectInfo;
+        use axum::http::HeaderMap;
+        use std::net::SocketAddr;
+
+        let db_client = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+        let owner_id = db_client.create_user("rebuild-owner", "pw").await.unwrap();
+        let state = AppState::for_test(db_client.clone());
+        let owner = || Extension("rebuild-owner".to_string());
+        let pull = || async {
+            let uri = "http://localhost/sub/rebuild-test?key=secret&target=clash"
+                .parse()
+                .unwrap();
+            let response = get_group_sub(
+                State(state.clone()),
+                ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 1))),
+                HeaderMap::new(),
+                Path("rebuild-test".to_string()),
+                Query::<SubQuery>::try_from_uri(&uri).unwrap(),
+            )
+            .await;
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        };
+
+        let cache = r#"
+proxies:
+  - { name: "香港 01", type: ss, server: a.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+  - { name: "美国 01", type: ss, server: b.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+"#;
+        let link_id = db_client
+            .create_link(
+                owner_id,
+                "clash",
+                false,
+                None,
+                None,
+                None,
+                "http://a",
+                Some(cache),
+                0,
+            )
+            .await
+            .unwrap();
+
+        // 从不自动刷新的链接组在保存后即可拉取
+        let mut req = group_request("rebuild-test");
+        req.cache_refresh_interval = 0;
+        req.rules.node_include = Some("香港".to_string());
+        let group = create_group(State(state.clone()), owner(), Json(req))
+            .await
+            .data
+            .unwrap();
+        let resp = put_group_link(
+            State(state.clone()),
+            owner(),
+            Path((group.id, link_id)),
+            Json(GroupLinkRequest { priority: 0 }),
+        )
+        .await;
+        assert_eq!(resp.code, BizCode::Success.code());
+        let (status, body) = pull().await;
+        assert!(status.is_success());
+        assert!(body.contains("香港 01") && !body.contains("美国 01"));
+
+        // 修改规则后立即生效
+        let mut req = group_request("rebuild-test");
+        req.cache_refresh_interval = 0;
+        req.rules.node_include = Some("美国".to_string());
+        let resp = update_group(State(state.clone()), owner(), Path(group.id), Json(req)).await;
+        assert_eq!(resp.code, BizCode::Success.code());
+        let (_, body) = pull().await;
+        assert!(body.contains("美国 01") && !body.contains("香港 01"));
+
+        db_client.delete_user(owner_id).await.unwrap();
+    }
+}
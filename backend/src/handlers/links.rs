@@ -0,0 +1,370 @@
+use crate::db::Link;
+use crate::handlers::groups::rebuild_group;
+use crate::handlers::{db_error_code, resolve_user_id};
+use crate::types::api_response::*;
+use crate::types::app_state::AppState;
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+// 支持的链接类型：clash 为 Clash 配置，uri 为逐行的分享链接（可整体 base64 编码），norm 为普通链接
+pub const LINK_TYPES: &[&str] = &["clash", "uri", "norm"];
+
+#[derive(Deserialize)]
+pub struct LinkRequest {
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(default)]
+    is_public: bool,
+    name: Option<String>,
+    slug: Option<String>,
+    description: Option<String>,
+    content: String, // 上游地址
+    #[serde(default)]
+    cache_refresh_interval: i32, // 缓存刷新间隔（秒），0 表示从不刷新
+}
+
+impl LinkRequest {
+    // 校验请求参数，返回错误信息
+    fn validate(&self) -> Result<(), &'static str> {
+        if !LINK_TYPES.contains(&self.type_.as_str()) {
+            return Err("不支持的链接类型");
+        }
+        if self.content.trim().is_empty() {
+            return Err("链接内容不能为空");
+        }
+        if self.cache_refresh_interval < 0 {
+            return Err("刷新间隔不能为负数");
+        }
+        Ok(())
+    }
+}
+
+// 获取当前用户的全部链接
+pub async fn list_links(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+) -> ApiResponse<Vec<Link>> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+
+    match state.db_client.get_links_by_user(user_id).await {
+        Ok(links) => ApiResponse::success(links),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 创建链接
+pub async fn create_link(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Json(req): Json<LinkRequest>,
+) -> ApiResponse<Link> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+    if let Err(msg) = req.validate() {
+        return ApiResponse::error(BizCode::BadRequest, Some(msg));
+    }
+
+    let created = state
+        .db_client
+        .create_link(
+            user_id,
+            &req.type_,
+            req.is_public,
+            req.name.as_deref(),
+            req.slug.as_deref(),
+            req.description.as_deref(),
+            &req.content,
+            None,
+            req.cache_refresh_interval,
+        )
+        .await;
+    let id = match created {
+        Ok(id) => id,
+        Err(e) => return link_error(&e),
+    };
+
+    match state.db_client.get_link_by_id(id).await {
+        Ok(link) => ApiResponse::success(link),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 获取单个链接
+pub async fn get_link(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+) -> ApiResponse<Link> {
+    match owned_link(&state, &username, id).await {
+        Ok(link) => ApiResponse::success(link),
+        Err(code) => ApiResponse::error(code, None),
+    }
+}
+
+// 更新链接，上游地址变化时清空旧缓存，并重新生成包含该链接的链接组
+pub async fn update_link(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+    Json(req): Json<LinkRequest>,
+) -> ApiResponse<Link> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+    if let Err(msg) = req.validate() {
+        return ApiResponse::error(BizCode::BadRequest, Some(msg));
+    }
+
+    let updated = state
+        .db_client
+        .update_link(
+            id,
+            user_id,
+            &req.type_,
+            req.is_public,
+            req.name.as_deref(),
+            req.slug.as_deref(),
+            req.description.as_deref(),
+            &req.content,
+            req.cache_refresh_interval,
+        )
+        .await;
+    match updated {
+        Ok(true) => {}
+        Ok(false) => return ApiResponse::error(BizCode::NotFound, None),
+        Err(e) => return link_error(&e),
+    }
+    for group_id in link_group_ids(&state, id).await {
+        rebuild_group(&state, group_id).await;
+    }
+
+    match state.db_client.get_link_by_id(id).await {
+        Ok(link) => ApiResponse::success(link),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 删除链接，并重新生成原本包含该链接的链接组
+pub async fn delete_link(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+    Path(id): Path<i64>,
+) -> ApiResponse<()> {
+    let user_id = match resolve_user_id(&state.db_client, &username).await {
+        Ok(user_id) => user_id,
+        Err(code) => return ApiResponse::error(code, None),
+    };
+
+    // 成员关系随链接级联删除，需在删除前查出所在的链接组
+    let group_ids = link_group_ids(&state, id).await;
+    match state.db_client.delete_link(id, user_id).await {
+        Ok(true) => {
+            for group_id in group_ids {
+                rebuild_group(&state, group_id).await;
+            }
+            ApiResponse::success_empty()
+        }
+        Ok(false) => ApiResponse::error(BizCode::NotFound, None),
+        Err(e) => ApiResponse::error(db_error_code(&e), None),
+    }
+}
+
+// 读取属于当前用户的链接，其他用户的链接按不存在处理
+async fn owned_link(state: &AppState, username: &str, id: i64) -> Result<Link, BizCode> {
+    let user_id = resolve_user_id(&state.db_client, username).await?;
+    match state.db_client.get_link_by_id(id).await {
+        Ok(link) if link.user_id == user_id => Ok(link),
+        Ok(_) => Err(BizCode::NotFound),
+        Err(e) => Err(db_error_code(&e)),
+    }
+}
+
+// 包含该链接的链接组，查询失败时只记录日志
+async fn link_group_ids(state: &AppState, id: i64) -> Vec<i64> {
+    state
+        .db_client
+        .get_group_ids_by_link(id)
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("failed to query groups of link {id}: {e}");
+            Vec::new()
+        })
+}
+
+fn link_error<T>(e: &sqlx::Error) -> ApiResponse<T> {
+    match db_error_code(e) {
+        BizCode::Conflict => ApiResponse::error(BizCode::Conflict, Some("slug 已被占用")),
+        code => ApiResponse::error(code, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::db::DbClient;
+    use crate::services::fetcher::Fetcher;
+
+    fn link_request(content: &str) -> LinkRequest {
+        LinkRequest {
+            type_: "clash".to_string(),
+            is_public: false,
+            name: Some("upstream".to_string()),
+            slug: None,
+            description: None,
+            content: content.to_string(),
+            cache_refresh_interval: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_links_are_scoped_to_owner() {
+        let db_client = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+        let owner_id = db_client.create_user("links-owner", "pw").await.unwrap();
+        let other_id = db_client.create_user("links-other", "pw").await.unwrap();
+        let state = AppState::for_test(db_client.clone());
+        let owner = || Extension("links-owner".to_string());
+        let other = || Extension("links-other".to_string());
+
+        let created = create_link(
+            State(state.clone()),
+            owner(),
+            Json(link_request("http://a")),
+        )
+        .await
+        .data
+        .unwrap();
+        assert_eq!(created.user_id, owner_id);
+
+        // 其他用户无法读取、修改或删除
+        let resp = get_link(State(state.clone()), other(), Path(created.id)).await;
+        assert_eq!(resp.code, BizCode::NotFound.code());
+        let resp = update_link(
+            State(state.clone()),
+            other(),
+            Path(created.id),
+            Json(link_request("http://b")),
+        )
+        .await;
+        assert_eq!(resp.code, BizCode::NotFound.code());
+        let resp = delete_link(State(state.clone()), other(), Path(created.id)).await;
+        assert_eq!(resp.code, BizCode::NotFound.code());
+        let listed = list_links(State(state.clone()), other())
+            .await
+            .data
+            .unwrap();
+        assert!(listed.is_empty());
+
+        // 所有者可以正常操作
+        let updated = update_link(
+            State(state.clone()),
+            owner(),
+            Path(created.id),
+            Json(link_request("http://b")),
+        )
+        .await
+        .data
+        .unwrap();
+        assert_eq!(updated.content, "http://b");
+        let resp = delete_link(State(state.clone()), owner(), Path(created.id)).await;
+        assert_eq!(resp.code, BizCode::Success.code());
+
+        db_client.delete_user(owner_id).await.unwrap();
+        db_client.delete_user(other_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_link_writes_rebuild_groups() {
+        let db_client = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+        let user_id = db_client.create_user("links-rebuild", "pw").await.unwrap();
+        // 修改地址后缓存被清空，重新生成时从这里拉取
+        let upstream = r#"
+proxies:
+  - { name: "新节点", type: ss, server: b.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+"#;
+        let state =
+            AppState::for_test_with_fetcher(db_client.clone(), Fetcher::Static(upstream.into()));
+        let owner = || Extension("links-rebuild".to_string());
+
+        let cache = r#"
+proxies:
+  - { name: "旧节点", type: ss, server: a.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+"#;
+        let link_id = db_client
+            .create_link(
+                user_id,
+                "clash",
+                false,
+                None,
+                None,
+                None,
+                "http://a",
+                Some(cache),
+                0,
+            )
+            .await
+            .unwrap();
+        let group_id = db_client
+            .create_link_group(
+                user_id,
+                "Team",
+                "links-rebuild",
+                None,
+                None,
+                false,
+                None,
+                0,
+                &Default::default(),
+            )
+            .await
+            .unwrap();
+        let cached_names = || async {
+            let group = db_client.get_link_group_by_id(group_id).await.unwrap();
+            let config: serde_yaml::Value =
+                serde_yaml::from_str(&group.cache_content.unwrap()).unwrap();
+            config["proxies"]
+                .as_sequence()
+                .unwrap()
+                .iter()
+                .map(|proxy| proxy["name"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(
+            db_client
+                .add_link_to_group(group_id, link_id, user_id)
+                .await
+                .unwrap()
+        );
+        assert!(state.group_builder.rebuild(group_id).await.unwrap());
+        assert_eq!(cached_names().await, vec!["旧节点"]);
+
+        // 修改上游地址后链接组立即使用新内容
+        let resp = update_link(
+            State(state.clone()),
+            owner(),
+            Path(link_id),
+            Json(link_request("http://b")),
+        )
+        .await;
+        assert_eq!(resp.code, BizCode::Success.code());
+        assert_eq!(cached_names().await, vec!["新节点"]);
+
+        // 删除链接后链接组不再包含其节点
+        let resp = delete_link(State(state.clone()), owner(), Path(link_id)).await;
+        assert_eq!(resp.code, BizCode::Success.code());
+        assert!(cached_names().await.is_empty());
+
+        db_client.delete_user(user_id).await.unwrap();
+    }
+}
@@ -1,9 +1,15 @@
-use axum::{extract::{Form, State}};
-use serde::{Deserialize, Serialize};
-use std::{ sync::{Arc}};
-use crate::types::app_state::AppState;
-use crate::db::{DbClient};
+use crate::db::DbClient;
+use crate::handlers::client_ip;
 use crate::types::api_response::*;
+use crate::types::app_state::AppState;
+use crate::types::login_limiter::LoginBlocked;
+use crate::utils::password::{self, PasswordCheck};
+use axum::{
+    extract::{ConnectInfo, Form, State},
+    http::HeaderMap,
+};
+use serde::{Deserialize, Serialize};
+use std::{net::SocketAddr, sync::Arc};
 
 #[derive(Deserialize)]
 pub struct LoginForm {
@@ -16,32 +22,78 @@ pub struct LoginResponse {
     token: String
 }
 #[axum::debug_handler]
-pub async fn login(State(state): State<Arc<AppState>>, Form(form): Form<LoginForm>)
-    -> ApiResponse<LoginResponse> {
-    // 验证用户名密码
+pub async fn login(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Form(form): Form<LoginForm>,
+) -> ApiResponse<LoginResponse> {
     let username = form.username.clone();
     let password = form.password.clone();
+
+    // 失败次数过多时直接拒绝，不再校验密码
+    let ip = client_ip(&headers, peer, state.config.server.trust_proxy_headers);
+    let attempt = match state.login_limiter.check(ip, &username) {
+        Ok(attempt) => attempt,
+        Err(blocked) => {
+            let msg = match blocked {
+                LoginBlocked::Backoff(_) => "登录尝试过于频繁，请稍后再试",
+                LoginBlocked::Locked(_) => "账户已被临时锁定，请稍后再试",
+            };
+            log::warn!("login for {username} from {ip} rejected: {blocked:?}");
+            return ApiResponse::error(BizCode::TooManyRequests, Some(msg))
+                .with_retry_after(blocked.retry_after_secs());
+        }
+    };
+
+    // 验证用户名密码
     if verify_pwd_hash(&username, &password, state.db_client.clone()).await {
+        attempt.succeed();
 
-        let token = state.sessions.add_session(&username);
-        let resp = LoginResponse {
-            token
-        };
-        ApiResponse::success(resp)
+        match state.sessions.add_session(&username).await {
+            Ok(token) => ApiResponse::success(LoginResponse { token }),
+            Err(e) => {
+                log::error!("failed to create session for {username}: {e}");
+                ApiResponse::error(BizCode::ServerError, None)
+            }
+        }
 
     } else {
+        attempt.fail();
         // 错误响应
         ApiResponse::error(BizCode::Unauthorized, Some("登录失败"))
     }
 }
 
+async fn verify_pwd_hash(user_name: &str, password: &str, db_client: DbClient) -> bool {
+    let user_info = db_client.get_user_by_username(user_name).await.ok();
+    let password = password.to_string();
 
-async fn verify_pwd_hash (user_name:&str, pw_hash:&str, db_client: DbClient) -> bool{
-    let user_info = db_client.get_user_by_username(user_name).await;
-    match user_info {
-        Ok(user_info) => {user_info.pwd_hash == pw_hash},
-        Err(e) => {false}
-    }
-}
+    // Argon2 计算较慢，放到阻塞线程池中执行
+    let (user_info, password, check) = tokio::task::spawn_blocking(move || {
+        let check = match &user_info {
+            Some(user_info) => password::verify_password(&password, &user_info.pwd_hash),
+            None => {
+                password::verify_dummy(&password);
+                PasswordCheck::Invalid
+            }
+        };
+        (user_info, password, check)
+    })
+    .await
+    .expect("Password verification task panicked");
 
+    // 明文或旧参数的记录在登录成功后透明升级
+    if let (Some(user_info), PasswordCheck::NeedsRehash) = (&user_info, &check)
+        && let Err(e) = db_client
+            .update_user_password(user_info.id, &password)
+            .await
+    {
+        log::error!(
+            "failed to upgrade password hash for user {}: {e}",
+            user_info.id
+        );
+    }
 
+    check.is_valid()
+}
@@ -0,0 +1,220 @@
+use crate::config::SubscriptionConfig;
+use crate::db::LinkGroup;
+use crate::handlers::client_ip;
+use crate::proxy::output::{self, Target};
+use crate::proxy::userinfo::SubscriptionUserinfo;
+use crate::services::access_log;
+use crate::types::api_response::*;
+use crate::types::app_state::AppState;
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, HeaderValue, header},
+    response::{IntoResponse, Response},
+};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+
+#[derive(Deserialize)]
+pub struct SubQuery {
+    key: Option<String>,
+    target: Option<String>, // 输出格式，优先于按 User-Agent 的选择
+}
+
+// 对外提供链接组订阅内容，Clash 等客户端直接拉取该地址
+pub async fn get_group_sub(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+    Query(query): Query<SubQuery>,
+) -> Response {
+    let group = match state.db_client.get_group_by_slug(&slug).await {
+        Ok(group) => group,
+        Err(sqlx::Error::RowNotFound) => {
+            return ApiResponse::<()>::error(BizCode::NotFound, None).into_response();
+        }
+        Err(_) => {
+            return ApiResponse::<()>::error(BizCode::ServerError, None).into_response();
+        }
+    };
+
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let negotiated = negotiate(
+        query.target.as_deref(),
+        user_agent.as_deref(),
+        &state.config.subscription,
+    );
+    match &negotiated {
+        Some((target, reason)) => {
+            log::debug!("group {slug} served as {target:?} by {reason}, user-agent {user_agent:?}")
+        }
+        None => log::debug!("group {slug} requested unknown target {:?}", query.target),
+    }
+
+    let group_id = group.id;
+    let mut response = serve_group(group, &query, negotiated.map(|(target, _)| target));
+    // 未显式指定格式时响应内容随 User-Agent 变化
+    if query.target.as_deref().is_none_or(str::is_empty) {
+        response
+            .headers_mut()
+            .insert(header::VARY, HeaderValue::from_static("user-agent"));
+    }
+
+    // 汇总组内各上游的流量信息，让客户端依然能显示剩余流量
+    if response.status().is_success() {
+        match state.db_client.get_group_userinfos(group_id).await {
+            Ok(values) => {
+                let infos = values
+                    .iter()
+                    .filter_map(|value| SubscriptionUserinfo::parse(value));
+                if let Some(info) = SubscriptionUserinfo::aggregate(infos)
+                    && let Ok(value) = HeaderValue::from_str(&info.to_string())
+                {
+                    response
+                        .headers_mut()
+                        .insert("subscription-userinfo", value);
+                }
+            }
+            Err(e) => log::warn!("failed to load userinfo of group {group_id}: {e}"),
+        }
+    }
+
+    // 记录本次拉取，写入失败不影响响应；密钥脱敏保存，User-Agent 截断
+    let client_ip = client_ip(&headers, peer, state.config.server.trust_proxy_headers);
+    let status = response.status().as_u16();
+    let key_used = query.key.as_deref().and_then(access_log::mask_key);
+    let db_client = state.db_client.clone();
+    tokio::spawn(async move {
+        let result = db_client
+            .create_group_access_log(
+                group_id,
+                &client_ip.to_string(),
+                user_agent.as_deref().map(access_log::truncate_user_agent),
+                key_used.as_deref(),
+                status,
+            )
+            .await;
+        if let Err(e) = result {
+            log::warn!("failed to record access to group {group_id}: {e}");
+        }
+    });
+
+    response
+}
+
+// 决定输出格式：显式的 target 参数优先，其次按配置的规则匹配 User-Agent，都没有时使用默认格式
+// 同时返回决定依据用于日志；target 参数无法识别时返回 None
+fn negotiate(
+    query_target: Option<&str>,
+    user_agent: Option<&str>,
+    config: &SubscriptionConfig,
+) -> Option<(Target, String)> {
+    if let Some(value) = query_target.filter(|value| !value.is_empty()) {
+        return Target::parse(value).map(|target| (target, format!("query target={value}")));
+    }
+    let rule = user_agent.and_then(|user_agent| config.match_user_agent(user_agent));
+    Some(match rule {
+        Some(rule) => (
+            rule.target,
+            format!("user-agent keyword {:?}", rule.keyword),
+        ),
+        None => (config.default_target, "default".to_string()),
+    })
+}
+
+// 校验访问权限并生成订阅响应
+fn serve_group(group: LinkGroup, query: &SubQuery, target: Option<Target>) -> Response {
+    // 未公开的链接组按不存在处理，避免暴露 slug
+    if !group.is_public {
+        return ApiResponse::<()>::error(BizCode::NotFound, None).into_response();
+    }
+
+    // 设置了访问密钥时必须携带正确的 key
+    if let Some(expected) = group.key.as_deref().filter(|k| !k.is_empty()) {
+        let provided = query.key.as_deref().unwrap_or_default();
+        if !bool::from(provided.as_bytes().ct_eq(expected.as_bytes())) {
+            return ApiResponse::<()>::error(BizCode::Forbidden, Some("访问密钥错误"))
+                .into_response();
+        }
+    }
+
+    let Some(target) = target else {
+        return ApiResponse::<()>::error(BizCode::BadRequest, Some("不支持的输出格式"))
+            .into_response();
+    };
+
+    let Some(content) = group.cache_content else {
+        return ApiResponse::<()>::error(BizCode::NotFound, Some("订阅内容尚未生成"))
+            .into_response();
+    };
+
+    let rendered = match output::render(target, &content) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            log::error!("failed to render group {} as {target:?}: {e}", group.id);
+            return ApiResponse::<()>::error(BizCode::ServerError, None).into_response();
+        }
+    };
+    for node in &rendered.skipped {
+        log::debug!(
+            "group {} skipped node {} for {target:?}: {}",
+            group.id,
+            node.name,
+            node.reason
+        );
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(target.content_type()),
+    );
+    // Clash 客户端会读取文件名作为配置名称
+    let filename = utf8_percent_encode(&group.name, NON_ALPHANUMERIC);
+    if let Ok(value) = HeaderValue::from_str(&format!("attachment; filename*=UTF-8''{filename}")) {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    // 告知客户端自动更新间隔（小时）
+    if group.cache_refresh_interval > 0 {
+        let hours = (group.cache_refresh_interval + 3599) / 3600;
+        headers.insert("profile-update-interval", HeaderValue::from(hours));
+    }
+
+    (headers, rendered.content).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        let config = SubscriptionConfig::default();
+        let target = |query: Option<&str>, user_agent: Option<&str>| {
+            negotiate(query, user_agent, &config).map(|(target, _)| target)
+        };
+        assert_eq!(target(None, Some("clash.meta")), Some(Target::Clash));
+        assert_eq!(
+            target(None, Some("SFI/1.11.4 (sing-box 1.11.4)")),
+            Some(Target::Singbox)
+        );
+        assert_eq!(
+            target(None, Some("Shadowrocket/2070")),
+            Some(Target::Base64)
+        );
+        assert_eq!(target(None, None), Some(Target::Clash));
+        assert_eq!(target(Some(""), Some("v2rayN/7.4.2")), Some(Target::Base64));
+        // 显式参数优先于 User-Agent
+        assert_eq!(
+            target(Some("singbox"), Some("clash.meta")),
+            Some(Target::Singbox)
+        );
+        assert_eq!(target(Some("v2"), Some("clash.meta")), None);
+    }
+}
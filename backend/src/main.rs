@@ -1,10 +1,15 @@
 // main.rs
+mod cli;
+mod config;
 mod db;
 mod handlers;
 mod middlewares;
+mod proxy;
 mod server;
+mod services;
 mod types;
+mod utils;
 
 fn main() {
-    server::main();
+    cli::main();
 }
@@ -1,14 +1,11 @@
+use crate::types::api_response::*;
+use crate::types::app_state::AppState;
+use axum::extract::State;
 use axum::{
-    response::{Redirect, IntoResponse},
-    http::{HeaderMap, header},
-    middleware::{Next},
-    extract::{Request, Extension},
+    Extension, extract::Request, http::HeaderMap, middleware::Next, response::IntoResponse,
 };
-use std::{sync::{Arc}};
-use axum::extract::State;
-use crate::types::app_state::AppState;
-use crate::types::session_store::SessionStore;
-use crate::types::api_response::*;
+use serde::Serialize;
+use std::sync::Arc;
 
 // 鉴权函数
 pub(crate) async fn auth_middleware(
@@ -17,30 +14,16 @@ pub(crate) async fn auth_middleware(
     next: Next,
 ) -> impl IntoResponse {
     // 1. 从Authorization头中获取token
-    let token = req.headers()
-        .get("Authorization")
-        .and_then(|auth_header| auth_header.to_str().ok())
-        .and_then(|auth_value| {
-            // 通常Authorization头的格式是 "Bearer <token>"
-            if auth_value.starts_with("Bearer ") {
-                Some(auth_value[7..].trim().to_string())
-            } else {
-                // 也可以支持其他格式或者直接就是token
-                Some(auth_value.trim().to_string())
-            }
-        })
-        .unwrap_or_default();
+    let token = bearer_token(req.headers());
 
-    // 2. 验证会话（安全持有锁）
-    let username = {
-        state.sessions.get_user(&token)
-    };
+    // 2. 验证会话
+    let username = state.sessions.get_user(&token).await;
 
     match username {
         Some(username) => {
             // 3. 添加用户名到请求扩展，用于将通过验证的用户名传递给后续执行的 handlers
             req.extensions_mut().insert(username);
-            // 4. 释放锁后执行后续请求
+            // 4. 执行后续请求
             next.run(req).await
         }
         None => {
@@ -51,13 +34,42 @@ pub(crate) async fn auth_middleware(
 }
 
 
-async fn logout(Extension(sessions): Extension<SessionStore>) -> impl IntoResponse {
-    // 清除会话 (实际应用中应从请求中获取token)
+// 从 Authorization 头中取出令牌
+fn bearer_token(headers: &HeaderMap) -> String {
+    headers
+        .get("Authorization")
+        .and_then(|auth_header| auth_header.to_str().ok())
+        .map(|auth_value| {
+            // 通常Authorization头的格式是 "Bearer <token>"
+            match auth_value.strip_prefix("Bearer ") {
+                Some(token) => token.trim().to_string(),
+                // 也可以支持其他格式或者直接就是token
+                None => auth_value.trim().to_string(),
+            }
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogoutAllResponse {
+    revoked: u64, // 被注销的会话数量
+}
 
-    // 清除cookie并重定向
-    let mut headers = HeaderMap::new();
-    headers.insert(header::SET_COOKIE,
-                   "session_token=; Path=/; Max-Age=0".parse().unwrap());
+// 注销当前令牌
+pub(crate) async fn logout(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ApiResponse<()> {
+    let token = bearer_token(&headers);
+    state.sessions.remove_session(&token).await;
+    ApiResponse::success_empty()
+}
 
-    (headers, Redirect::to("/"))
+// 注销当前用户的全部会话
+pub(crate) async fn logout_all(
+    State(state): State<Arc<AppState>>,
+    Extension(username): Extension<String>,
+) -> ApiResponse<LogoutAllResponse> {
+    let revoked = state.sessions.remove_user_sessions(&username).await;
+    ApiResponse::success(LogoutAllResponse { revoked })
 }
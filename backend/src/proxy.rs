@@ -0,0 +1,13 @@
+pub(crate) mod clash;
+pub(crate) mod dedup;
+pub(crate) mod filter;
+pub(crate) mod node;
+pub(crate) mod output;
+pub(crate) mod quanx;
+pub(crate) mod region;
+pub(crate) mod rename;
+pub(crate) mod singbox;
+pub(crate) mod surge;
+pub(crate) mod template;
+pub(crate) mod uri;
+pub(crate) mod userinfo;
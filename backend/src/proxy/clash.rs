@@ -0,0 +1,183 @@
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+
+// 合并配置中生成的策略组名称
+pub const SELECT_GROUP: &str = "节点选择";
+pub const AUTO_GROUP: &str = "自动选择";
+// 自动选择组的测速地址与间隔（秒）
+pub const TEST_URL: &str = "http://www.gstatic.com/generate_204";
+pub const TEST_INTERVAL: u64 = 300;
+
+// 从 Clash 配置中取出 proxies 列表，每个节点保持原始的字段
+pub fn parse_proxies(content: &str) -> Result<Vec<Mapping>, serde_yaml::Error> {
+    let config: Value = serde_yaml::from_str(content)?;
+    let proxies = match config.get("proxies") {
+        Some(Value::Sequence(list)) => list
+            .iter()
+            .filter_map(|proxy| proxy.as_mapping().cloned())
+            .collect(),
+        _ => Vec::new(),
+    };
+    Ok(proxies)
+}
+
+// 读取节点名称
+pub fn proxy_name(proxy: &Mapping) -> Option<&str> {
+    proxy.get("name").and_then(Value::as_str)
+}
+
+// 为节点设置名称
+pub fn set_proxy_name(proxy: &mut Mapping, name: &str) {
+    proxy.insert(Value::from("name"), Value::from(name));
+}
+
+// 节点名称去重器，重名节点依次追加 " 2"、" 3" 等后缀
+pub struct NameDeduper {
+    used: HashSet<String>,
+}
+
+impl NameDeduper {
+    pub fn new() -> Self {
+        // 预留策略组与内置策略的名称，避免节点与之冲突
+        Self::with_reserved([SELECT_GROUP, AUTO_GROUP])
+    }
+
+    // 预留指定名称以及内置策略的名称
+    pub fn with_reserved<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let used = names
+            .into_iter()
+            .chain(["DIRECT", "REJECT"])
+            .map(String::from)
+            .collect();
+        Self { used }
+    }
+
+    pub fn unique(&mut self, name: &str) -> String {
+        let mut candidate = name.to_string();
+        let mut index = 2;
+        while self.used.contains(&candidate) {
+            candidate = format!("{name} {index}");
+            index += 1;
+        }
+        self.used.insert(candidate.clone());
+        candidate
+    }
+}
+
+// 将多个来源的节点合并为一份完整的 Clash 配置
+pub fn merge_config(sources: Vec<Vec<Mapping>>) -> String {
+    let mut deduper = NameDeduper::new();
+    let mut proxies = Vec::new();
+    let mut names = Vec::new();
+
+    for proxy in sources.into_iter().flatten() {
+        let Some(name) = proxy_name(&proxy) else {
+            continue;
+        };
+        let name = deduper.unique(name);
+        let mut proxy = proxy;
+        set_proxy_name(&mut proxy, &name);
+        names.push(Value::from(name));
+        proxies.push(Value::Mapping(proxy));
+    }
+
+    let mut select = Mapping::new();
+    select.insert("name".into(), SELECT_GROUP.into());
+    select.insert("type".into(), "select".into());
+    let mut select_proxies = vec![Value::from(AUTO_GROUP), Value::from("DIRECT")];
+    select_proxies.extend(names.iter().cloned());
+    select.insert("proxies".into(), Value::Sequence(select_proxies));
+
+    let mut auto = Mapping::new();
+    auto.insert("name".into(), AUTO_GROUP.into());
+    auto.insert("type".into(), "url-test".into());
+    auto.insert("url".into(), TEST_URL.into());
+    auto.insert("interval".into(), TEST_INTERVAL.into());
+    // url-test 组不能为空，没有节点时退化为直连
+    if names.is_empty() {
+        names.push(Value::from("DIRECT"));
+    }
+    auto.insert("proxies".into(), Value::Sequence(names));
+
+    let mut config = Mapping::new();
+    config.insert("mixed-port".into(), 7890.into());
+    config.insert("allow-lan".into(), false.into());
+    config.insert("mode".into(), "rule".into());
+    config.insert("log-level".into(), "info".into());
+    config.insert("proxies".into(), Value::Sequence(proxies));
+    config.insert(
+        "proxy-groups".into(),
+        Value::Sequence(vec![Value::Mapping(select), Value::Mapping(auto)]),
+    );
+    config.insert(
+        "rules".into(),
+        Value::Sequence(vec![Value::from(format!("MATCH,{SELECT_GROUP}"))]),
+    );
+
+    serde_yaml::to_string(&config).expect("Failed to serialize Clash config")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UPSTREAM_A: &str = r#"
+proxies:
+  - { name: "HK 01", type: ss, server: a.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+  - { name: "JP 01", type: trojan, server: b.example.com, port: 443, password: pw }
+proxy-groups:
+  - { name: PROXY, type: select, proxies: ["HK 01", "JP 01"] }
+"#;
+
+    const UPSTREAM_B: &str = r#"
+proxies:
+  - { name: "HK 01", type: vmess, server: c.example.com, port: 443, uuid: 00000000-0000-0000-0000-000000000000, alterId: 0, cipher: auto }
+"#;
+
+    #[test]
+    fn test_merge_dedups_names() {
+        let a = parse_proxies(UPSTREAM_A).unwrap();
+        let b = parse_proxies(UPSTREAM_B).unwrap();
+        let merged = merge_config(vec![a, b]);
+
+        let config: Value = serde_yaml::from_str(&merged).unwrap();
+        let names: Vec<&str> = config["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|proxy| proxy["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["HK 01", "JP 01", "HK 01 2"]);
+
+        // 原有字段保持不变
+        assert_eq!(
+            config["proxies"][2]["server"].as_str(),
+            Some("c.example.com")
+        );
+
+        // 策略组列出全部节点
+        let groups = config["proxy-groups"].as_sequence().unwrap();
+        assert_eq!(groups[0]["name"].as_str(), Some(SELECT_GROUP));
+        assert_eq!(groups[0]["proxies"].as_sequence().unwrap().len(), 5);
+        assert_eq!(groups[1]["proxies"].as_sequence().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_merge_without_proxies() {
+        let merged = merge_config(vec![parse_proxies("port: 7890").unwrap()]);
+        let config: Value = serde_yaml::from_str(&merged).unwrap();
+        assert!(config["proxies"].as_sequence().unwrap().is_empty());
+        assert_eq!(
+            config["proxy-groups"][1]["proxies"][0].as_str(),
+            Some("DIRECT")
+        );
+    }
+
+    #[test]
+    fn test_reserved_names() {
+        let mut deduper = NameDeduper::new();
+        assert_eq!(deduper.unique("DIRECT"), "DIRECT 2");
+        assert_eq!(deduper.unique("A"), "A");
+        assert_eq!(deduper.unique("A"), "A 2");
+    }
+}
@@ -0,0 +1,251 @@
+use crate::proxy::clash;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+// 不同协议用于鉴权的字段，组成去重键的一部分
+const CREDENTIAL_KEYS: &[&str] = &[
+    "uuid",
+    "password",
+    "username",
+    "auth-str",
+    "auth",
+    "private-key",
+    "psk",
+];
+
+// 重复节点的处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum DedupPolicy {
+    #[default]
+    Off, // 不去重
+    KeepFirst,    // 保留组内顺序靠前的节点
+    KeepPriority, // 保留所属链接优先级最高的节点，优先级相同时保留靠前的
+    KeepAll,      // 全部保留，在重复节点名称后追加来源链接名称
+}
+
+// 参与去重的节点
+pub struct DedupNode {
+    pub link_id: i64,
+    pub link_name: String,
+    pub original_name: String, // 重命名前的名称，仅用于预览
+    pub priority: i32,         // 所属链接在组内的优先级
+    pub proxy: Mapping,
+}
+
+impl DedupNode {
+    fn name(&self) -> &str {
+        clash::proxy_name(&self.proxy).unwrap_or_default()
+    }
+
+    fn to_ref(&self) -> NodeRef {
+        NodeRef {
+            link_id: self.link_id,
+            name: self.name().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeRef {
+    pub link_id: i64,
+    pub name: String,
+}
+
+// 一组被判定为重复的节点；不包含鉴权信息，避免在预览中泄露
+#[derive(Debug, Serialize)]
+pub struct DuplicateSet {
+    pub protocol: String,
+    pub server: String,
+    pub port: u64,
+    pub kept: Vec<NodeRef>,
+    pub dropped: Vec<NodeRef>,
+}
+
+// 由协议、服务器、端口与鉴权字段组成的去重键，缺少服务器或端口的节点不参与去重
+fn node_key(proxy: &Mapping) -> Option<(String, String, u64, String)> {
+    let text = |key: &str| match proxy.get(key)? {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    };
+    let protocol = text("type")?.to_ascii_lowercase();
+    let server = text("server")?.to_ascii_lowercase();
+    let port = proxy.get("port").and_then(|port| match port {
+        Value::Number(port) => port.as_u64(),
+        Value::String(port) => port.parse().ok(),
+        _ => None,
+    })?;
+    let credential = CREDENTIAL_KEYS
+        .iter()
+        .map(|key| text(key).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some((protocol, server, port, credential))
+}
+
+// 按策略合并重复节点，返回保留的节点（保持原有顺序）与合并报告
+pub fn dedup(nodes: Vec<DedupNode>, policy: DedupPolicy) -> (Vec<DedupNode>, Vec<DuplicateSet>) {
+    if policy == DedupPolicy::Off {
+        return (nodes, Vec::new());
+    }
+
+    // 按首次出现的顺序收集重复组
+    let mut index: HashMap<_, usize> = HashMap::new();
+    let mut sets: Vec<Vec<usize>> = Vec::new();
+    for (i, node) in nodes.iter().enumerate() {
+        let Some(key) = node_key(&node.proxy) else {
+            continue;
+        };
+        match index.get(&key) {
+            Some(&set) => sets[set].push(i),
+            None => {
+                index.insert(key, sets.len());
+                sets.push(vec![i]);
+            }
+        }
+    }
+
+    let mut nodes: Vec<Option<DedupNode>> = nodes.into_iter().map(Some).collect();
+    let mut report = Vec::new();
+    for members in sets.into_iter().filter(|members| members.len() > 1) {
+        let first = nodes[members[0]].as_ref().expect("Node is taken once");
+        let (protocol, server, port, _) = node_key(&first.proxy).expect("Key exists");
+        let mut set = DuplicateSet {
+            protocol,
+            server,
+            port,
+            kept: Vec::new(),
+            dropped: Vec::new(),
+        };
+
+        if policy == DedupPolicy::KeepAll {
+            for &i in &members[1..] {
+                let node = nodes[i].as_mut().expect("Node is taken once");
+                let name = format!("{} [{}]", node.name(), node.link_name);
+                clash::set_proxy_name(&mut node.proxy, &name);
+            }
+            set.kept = members
+                .iter()
+                .filter_map(|&i| nodes[i].as_ref())
+                .map(DedupNode::to_ref)
+                .collect();
+        } else {
+            let winner = match policy {
+                DedupPolicy::KeepPriority => members
+                    .iter()
+                    .copied()
+                    .min_by_key(|&i| {
+                        Reverse(nodes[i].as_ref().map_or(i32::MIN, |node| node.priority))
+                    })
+                    .expect("Set is not empty"),
+                _ => members[0],
+            };
+            for &i in &members {
+                let node = nodes[i].as_ref().expect("Node is taken once");
+                if i == winner {
+                    set.kept.push(node.to_ref());
+                } else {
+                    set.dropped.push(node.to_ref());
+                }
+            }
+            for &i in members.iter().filter(|&&i| i != winner) {
+                nodes[i] = None;
+            }
+        }
+        report.push(set);
+    }
+
+    (nodes.into_iter().flatten().collect(), report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(link_id: i64, priority: i32, yaml: &str) -> DedupNode {
+        DedupNode {
+            link_id,
+            link_name: format!("L{link_id}"),
+            original_name: String::new(),
+            priority,
+            proxy: serde_yaml::from_str(yaml).unwrap(),
+        }
+    }
+
+    fn nodes() -> Vec<DedupNode> {
+        vec![
+            node(
+                1,
+                0,
+                "{ name: HK 01, type: ss, server: a.example.com, port: 443, cipher: aes-128-gcm, password: pw }",
+            ),
+            node(
+                1,
+                0,
+                "{ name: JP 01, type: ss, server: b.example.com, port: 443, cipher: aes-128-gcm, password: pw }",
+            ),
+            node(
+                2,
+                5,
+                "{ name: 香港 A, type: ss, server: A.example.com, port: '443', cipher: aes-128-gcm, password: pw }",
+            ),
+            // 鉴权信息不同的不算重复
+            node(
+                2,
+                5,
+                "{ name: 香港 B, type: ss, server: a.example.com, port: 443, cipher: aes-128-gcm, password: other }",
+            ),
+        ]
+    }
+
+    fn names(nodes: &[DedupNode]) -> Vec<&str> {
+        nodes.iter().map(DedupNode::name).collect()
+    }
+
+    #[test]
+    fn test_off_keeps_everything() {
+        let (kept, report) = dedup(nodes(), DedupPolicy::Off);
+        assert_eq!(kept.len(), 4);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_keep_first() {
+        let (kept, report) = dedup(nodes(), DedupPolicy::KeepFirst);
+        assert_eq!(names(&kept), vec!["HK 01", "JP 01", "香港 B"]);
+        assert_eq!(report.len(), 1);
+        assert_eq!(
+            (report[0].server.as_str(), report[0].port),
+            ("a.example.com", 443)
+        );
+        assert_eq!(
+            report[0].dropped,
+            vec![NodeRef {
+                link_id: 2,
+                name: "香港 A".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_keep_priority() {
+        let (kept, report) = dedup(nodes(), DedupPolicy::KeepPriority);
+        assert_eq!(names(&kept), vec!["JP 01", "香港 A", "香港 B"]);
+        assert_eq!(report[0].kept[0].link_id, 2);
+    }
+
+    #[test]
+    fn test_keep_all_suffixes_names() {
+        let (kept, report) = dedup(nodes(), DedupPolicy::KeepAll);
+        assert_eq!(
+            names(&kept),
+            vec!["HK 01", "JP 01", "香港 A [L2]", "香港 B"]
+        );
+        assert_eq!(report[0].kept.len(), 2);
+        assert!(report[0].dropped.is_empty());
+    }
+}
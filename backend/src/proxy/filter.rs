@@ -0,0 +1,77 @@
+use regex::Regex;
+use serde::Serialize;
+
+// 节点被过滤的原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", content = "pattern", rename_all = "snake_case")]
+pub enum FilterVerdict {
+    Kept,
+    NotIncluded,      // 设置了保留规则但一条都未匹配
+    Excluded(String), // 匹配了该排除规则
+}
+
+// 按节点名称过滤的规则集
+#[derive(Debug, Default)]
+pub struct NodeFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl NodeFilter {
+    // 由每行一个正则的文本构造，空行被忽略
+    pub fn new(include: Option<&str>, exclude: Option<&str>) -> Result<Self, regex::Error> {
+        Ok(Self {
+            include: compile_rules(include)?,
+            exclude: compile_rules(exclude)?,
+        })
+    }
+
+    pub fn check(&self, name: &str) -> FilterVerdict {
+        if !self.include.is_empty() && !self.include.iter().any(|re| re.is_match(name)) {
+            return FilterVerdict::NotIncluded;
+        }
+        match self.exclude.iter().find(|re| re.is_match(name)) {
+            Some(re) => FilterVerdict::Excluded(re.as_str().to_string()),
+            None => FilterVerdict::Kept,
+        }
+    }
+}
+
+// 拆分规则文本，返回去除空白后的非空行
+pub fn split_rules(rules: &str) -> impl Iterator<Item = &str> {
+    rules.lines().map(str::trim).filter(|line| !line.is_empty())
+}
+
+fn compile_rules(rules: Option<&str>) -> Result<Vec<Regex>, regex::Error> {
+    rules
+        .map(split_rules)
+        .into_iter()
+        .flatten()
+        .map(Regex::new)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_include_and_exclude() {
+        let filter =
+            NodeFilter::new(Some("香港|HK\n\n日本"), Some("剩余流量\n(?i)expire")).unwrap();
+        assert_eq!(filter.check("HK 01"), FilterVerdict::Kept);
+        assert_eq!(filter.check("日本 02"), FilterVerdict::Kept);
+        assert_eq!(filter.check("美国 01"), FilterVerdict::NotIncluded);
+        assert_eq!(
+            filter.check("HK Expire 2025-01-01"),
+            FilterVerdict::Excluded("(?i)expire".to_string())
+        );
+    }
+
+    #[test]
+    fn test_empty_rules_keep_everything() {
+        let filter = NodeFilter::new(None, Some("  \n")).unwrap();
+        assert_eq!(filter.check("官网 example.com"), FilterVerdict::Kept);
+        assert!(NodeFilter::new(Some("(unclosed"), None).is_err());
+    }
+}
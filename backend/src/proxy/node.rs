@@ -0,0 +1,701 @@
+use serde_yaml::{Mapping, Value};
+
+// 与客户端格式无关的代理节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    pub server: String,
+    pub port: u16,
+    pub protocol: Protocol,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Protocol {
+    Shadowsocks {
+        cipher: String,
+        password: String,
+        plugin: Option<Plugin>,
+    },
+    ShadowsocksR {
+        cipher: String,
+        password: String,
+        protocol: String,
+        protocol_param: Option<String>,
+        obfs: String,
+        obfs_param: Option<String>,
+    },
+    Vmess {
+        uuid: String,
+        alter_id: u32,
+        cipher: String,
+        transport: Transport,
+        tls: Tls,
+    },
+    Trojan {
+        password: String,
+        transport: Transport,
+        tls: Tls, // Trojan 总是启用 TLS，enabled 字段被忽略
+    },
+    Vless {
+        uuid: String,
+        flow: Option<String>,
+        transport: Transport,
+        tls: Tls,
+    },
+    Hysteria2 {
+        password: String,
+        obfs_password: Option<String>, // 设置后使用 salamander 混淆
+        tls: Tls,
+    },
+}
+
+impl Protocol {
+    // 协议在 Clash 配置中的 type 字段
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Protocol::Shadowsocks { .. } => "ss",
+            Protocol::ShadowsocksR { .. } => "ssr",
+            Protocol::Vmess { .. } => "vmess",
+            Protocol::Trojan { .. } => "trojan",
+            Protocol::Vless { .. } => "vless",
+            Protocol::Hysteria2 { .. } => "hysteria2",
+        }
+    }
+}
+
+// Shadowsocks 插件，opts 保持 SIP003 的键值对
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    pub name: String,
+    pub opts: Vec<(String, String)>,
+}
+
+impl Plugin {
+    fn opt(&self, key: &str) -> Option<&str> {
+        self.opts
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Network {
+    #[default]
+    Tcp,
+    Ws,
+    Grpc,
+    H2,
+}
+
+impl Network {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Network::Tcp => "tcp",
+            Network::Ws => "ws",
+            Network::Grpc => "grpc",
+            Network::H2 => "h2",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "" | "tcp" => Some(Network::Tcp),
+            "ws" | "websocket" => Some(Network::Ws),
+            "grpc" => Some(Network::Grpc),
+            "h2" | "http" => Some(Network::H2),
+            _ => None,
+        }
+    }
+}
+
+// 传输层设置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transport {
+    pub network: Network,
+    pub path: Option<String>,         // ws / h2 路径
+    pub host: Option<String>,         // ws 的 Host 头或 h2 的域名
+    pub service_name: Option<String>, // gRPC 服务名
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tls {
+    pub enabled: bool,
+    pub sni: Option<String>,
+    pub insecure: bool, // 跳过证书校验
+    pub alpn: Vec<String>,
+    pub fingerprint: Option<String>, // uTLS 客户端指纹
+    pub reality: Option<Reality>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reality {
+    pub public_key: String,
+    pub short_id: Option<String>,
+}
+
+impl Node {
+    // 转换为 Clash (mihomo) 的节点配置
+    pub fn to_clash(&self) -> Mapping {
+        let mut proxy = Mapping::new();
+        proxy.insert("name".into(), self.name.as_str().into());
+        proxy.insert("type".into(), self.protocol.kind().into());
+        proxy.insert("server".into(), self.server.as_str().into());
+        proxy.insert("port".into(), self.port.into());
+
+        match &self.protocol {
+            Protocol::Shadowsocks {
+                cipher,
+                password,
+                plugin,
+            } => {
+                proxy.insert("cipher".into(), cipher.as_str().into());
+                proxy.insert("password".into(), password.as_str().into());
+                proxy.insert("udp".into(), true.into());
+                if let Some(plugin) = plugin {
+                    insert_plugin(&mut proxy, plugin);
+                }
+            }
+            Protocol::ShadowsocksR {
+                cipher,
+                password,
+                protocol,
+                protocol_param,
+                obfs,
+                obfs_param,
+            } => {
+                proxy.insert("cipher".into(), cipher.as_str().into());
+                proxy.insert("password".into(), password.as_str().into());
+                proxy.insert("protocol".into(), protocol.as_str().into());
+                insert_opt(&mut proxy, "protocol-param", protocol_param.as_deref());
+                proxy.insert("obfs".into(), obfs.as_str().into());
+                insert_opt(&mut proxy, "obfs-param", obfs_param.as_deref());
+                proxy.insert("udp".into(), true.into());
+            }
+            Protocol::Vmess {
+                uuid,
+                alter_id,
+                cipher,
+                transport,
+                tls,
+            } => {
+                proxy.insert("uuid".into(), uuid.as_str().into());
+                proxy.insert("alterId".into(), (*alter_id).into());
+                proxy.insert("cipher".into(), cipher.as_str().into());
+                proxy.insert("udp".into(), true.into());
+                insert_tls(&mut proxy, tls, "servername", true);
+                insert_transport(&mut proxy, transport);
+            }
+            Protocol::Trojan {
+                password,
+                transport,
+                tls,
+            } => {
+                proxy.insert("password".into(), password.as_str().into());
+                proxy.insert("udp".into(), true.into());
+                insert_tls(&mut proxy, tls, "sni", false);
+                insert_transport(&mut proxy, transport);
+            }
+            Protocol::Vless {
+                uuid,
+                flow,
+                transport,
+                tls,
+            } => {
+                proxy.insert("uuid".into(), uuid.as_str().into());
+                insert_opt(&mut proxy, "flow", flow.as_deref());
+                proxy.insert("udp".into(), true.into());
+                insert_tls(&mut proxy, tls, "servername", true);
+                insert_transport(&mut proxy, transport);
+            }
+            Protocol::Hysteria2 {
+                password,
+                obfs_password,
+                tls,
+            } => {
+                proxy.insert("password".into(), password.as_str().into());
+                if let Some(obfs_password) = obfs_password {
+                    proxy.insert("obfs".into(), "salamander".into());
+                    proxy.insert("obfs-password".into(), obfs_password.as_str().into());
+                }
+                insert_tls(&mut proxy, tls, "sni", false);
+            }
+        }
+        proxy
+    }
+
+    // 从 Clash 节点配置还原，不支持的协议返回原因
+    pub fn from_clash(proxy: &Mapping) -> Result<Self, String> {
+        let name = str_field(proxy, "name").ok_or("缺少名称")?;
+        let kind = str_field(proxy, "type").ok_or("缺少类型")?;
+        let server = str_field(proxy, "server").ok_or("缺少服务器地址")?;
+        let port = match proxy.get("port") {
+            Some(Value::Number(port)) => port.as_u64().and_then(|port| u16::try_from(port).ok()),
+            Some(Value::String(port)) => port.parse().ok(),
+            _ => None,
+        }
+        .ok_or("缺少端口")?;
+        let required = |key: &str| str_field(proxy, key).ok_or_else(|| format!("缺少 {key}"));
+
+        let protocol = match kind.as_str() {
+            "ss" => Protocol::Shadowsocks {
+                cipher: required("cipher")?,
+                password: required("password")?,
+                plugin: plugin_from_clash(proxy),
+            },
+            "ssr" => Protocol::ShadowsocksR {
+                cipher: required("cipher")?,
+                password: required("password")?,
+                protocol: required("protocol")?,
+                protocol_param: str_field(proxy, "protocol-param"),
+                obfs: required("obfs")?,
+                obfs_param: str_field(proxy, "obfs-param"),
+            },
+            "vmess" => Protocol::Vmess {
+                uuid: required("uuid")?,
+                alter_id: proxy
+                    .get("alterId")
+                    .and_then(Value::as_u64)
+                    .and_then(|id| u32::try_from(id).ok())
+                    .unwrap_or(0),
+                cipher: str_field(proxy, "cipher").unwrap_or_else(|| "auto".to_string()),
+                transport: transport_from_clash(proxy)?,
+                tls: tls_from_clash(proxy, "servername", bool_field(proxy, "tls")),
+            },
+            "trojan" => Protocol::Trojan {
+                password: required("password")?,
+                transport: transport_from_clash(proxy)?,
+                tls: tls_from_clash(proxy, "sni", true),
+            },
+            "vless" => Protocol::Vless {
+                uuid: required("uuid")?,
+                flow: str_field(proxy, "flow"),
+                transport: transport_from_clash(proxy)?,
+                tls: tls_from_clash(proxy, "servername", bool_field(proxy, "tls")),
+            },
+            "hysteria2" => Protocol::Hysteria2 {
+                password: required("password")?,
+                obfs_password: str_field(proxy, "obfs-password")
+                    .filter(|_| str_field(proxy, "obfs").as_deref() == Some("salamander")),
+                tls: tls_from_clash(proxy, "sni", true),
+            },
+            other => return Err(format!("不支持的协议 {other}")),
+        };
+
+        Ok(Self {
+            name,
+            server,
+            port,
+            protocol,
+        })
+    }
+}
+
+fn str_field(map: &Mapping, key: &str) -> Option<String> {
+    match map.get(key)? {
+        Value::String(value) if !value.is_empty() => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn bool_field(map: &Mapping, key: &str) -> bool {
+    map.get(key).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn tls_from_clash(proxy: &Mapping, sni_key: &str, enabled: bool) -> Tls {
+    let reality = proxy
+        .get("reality-opts")
+        .and_then(Value::as_mapping)
+        .and_then(|opts| {
+            Some(Reality {
+                public_key: str_field(opts, "public-key")?,
+                short_id: str_field(opts, "short-id"),
+            })
+        });
+    Tls {
+        enabled,
+        sni: str_field(proxy, sni_key),
+        insecure: bool_field(proxy, "skip-cert-verify"),
+        alpn: proxy
+            .get("alpn")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(|alpn| alpn.as_str().map(String::from))
+            .collect(),
+        fingerprint: str_field(proxy, "client-fingerprint"),
+        reality,
+    }
+}
+
+fn transport_from_clash(proxy: &Mapping) -> Result<Transport, String> {
+    let network = str_field(proxy, "network").unwrap_or_default();
+    let network = Network::parse(&network).ok_or_else(|| format!("不支持的传输方式 {network}"))?;
+    let opts = |key: &str| proxy.get(key).and_then(Value::as_mapping);
+    let mut transport = Transport {
+        network,
+        ..Default::default()
+    };
+    match network {
+        Network::Tcp => {}
+        Network::Ws => {
+            if let Some(opts) = opts("ws-opts") {
+                transport.path = str_field(opts, "path");
+                transport.host = opts
+                    .get("headers")
+                    .and_then(Value::as_mapping)
+                    .and_then(|headers| str_field(headers, "Host"));
+            }
+        }
+        Network::Grpc => {
+            transport.service_name =
+                opts("grpc-opts").and_then(|opts| str_field(opts, "grpc-service-name"));
+        }
+        Network::H2 => {
+            if let Some(opts) = opts("h2-opts") {
+                transport.path = str_field(opts, "path");
+                transport.host = opts
+                    .get("host")
+                    .and_then(Value::as_sequence)
+                    .and_then(|hosts| hosts.first())
+                    .and_then(|host| host.as_str().map(String::from));
+            }
+        }
+    }
+    Ok(transport)
+}
+
+// Clash 的 plugin 与 plugin-opts 还原为 SIP003 插件
+fn plugin_from_clash(proxy: &Mapping) -> Option<Plugin> {
+    let name = str_field(proxy, "plugin")?;
+    let empty = Mapping::new();
+    let opts = proxy
+        .get("plugin-opts")
+        .and_then(Value::as_mapping)
+        .unwrap_or(&empty);
+    let pair = |key: &str, opt: &str| str_field(opts, opt).map(|value| (key.to_string(), value));
+    let plugin = match name.as_str() {
+        "obfs" => Plugin {
+            name: "obfs-local".to_string(),
+            opts: [pair("obfs", "mode"), pair("obfs-host", "host")]
+                .into_iter()
+                .flatten()
+                .collect(),
+        },
+        "v2ray-plugin" => {
+            let mut pairs: Vec<(String, String)> = [
+                pair("mode", "mode"),
+                pair("host", "host"),
+                pair("path", "path"),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            if bool_field(opts, "tls") {
+                pairs.push(("tls".to_string(), String::new()));
+            }
+            Plugin { name, opts: pairs }
+        }
+        _ => Plugin {
+            opts: opts
+                .iter()
+                .filter_map(|(key, _)| {
+                    let key = key.as_str()?;
+                    Some((key.to_string(), str_field(opts, key)?))
+                })
+                .collect(),
+            name,
+        },
+    };
+    Some(plugin)
+}
+
+fn insert_opt(proxy: &mut Mapping, key: &str, value: Option<&str>) {
+    if let Some(value) = value {
+        proxy.insert(key.into(), value.into());
+    }
+}
+
+// 写入 TLS 相关字段；with_flag 为 false 的协议固定启用 TLS，不写 tls 字段
+fn insert_tls(proxy: &mut Mapping, tls: &Tls, sni_key: &str, with_flag: bool) {
+    if with_flag {
+        if !tls.enabled {
+            return;
+        }
+        proxy.insert("tls".into(), true.into());
+    }
+    insert_opt(proxy, sni_key, tls.sni.as_deref());
+    if tls.insecure {
+        proxy.insert("skip-cert-verify".into(), true.into());
+    }
+    if !tls.alpn.is_empty() {
+        let alpn = tls
+            .alpn
+            .iter()
+            .map(|alpn| Value::from(alpn.as_str()))
+            .collect();
+        proxy.insert("alpn".into(), Value::Sequence(alpn));
+    }
+    insert_opt(proxy, "client-fingerprint", tls.fingerprint.as_deref());
+    if let Some(reality) = &tls.reality {
+        let mut opts = Mapping::new();
+        opts.insert("public-key".into(), reality.public_key.as_str().into());
+        insert_opt(&mut opts, "short-id", reality.short_id.as_deref());
+        proxy.insert("reality-opts".into(), Value::Mapping(opts));
+    }
+}
+
+fn insert_transport(proxy: &mut Mapping, transport: &Transport) {
+    if transport.network == Network::Tcp {
+        return;
+    }
+    proxy.insert("network".into(), transport.network.as_str().into());
+
+    let mut opts = Mapping::new();
+    let key = match transport.network {
+        Network::Tcp => return,
+        Network::Ws => {
+            insert_opt(&mut opts, "path", transport.path.as_deref());
+            if let Some(host) = &transport.host {
+                let mut headers = Mapping::new();
+                headers.insert("Host".into(), host.as_str().into());
+                opts.insert("headers".into(), Value::Mapping(headers));
+            }
+            "ws-opts"
+        }
+        Network::Grpc => {
+            insert_opt(
+                &mut opts,
+                "grpc-service-name",
+                transport.service_name.as_deref(),
+            );
+            "grpc-opts"
+        }
+        Network::H2 => {
+            insert_opt(&mut opts, "path", transport.path.as_deref());
+            if let Some(host) = &transport.host {
+                opts.insert("host".into(), Value::Sequence(vec![host.as_str().into()]));
+            }
+            "h2-opts"
+        }
+    };
+    if !opts.is_empty() {
+        proxy.insert(key.into(), Value::Mapping(opts));
+    }
+}
+
+// SIP003 插件转换为 Clash 的 plugin 与 plugin-opts
+fn insert_plugin(proxy: &mut Mapping, plugin: &Plugin) {
+    let mut opts = Mapping::new();
+    let name = match plugin.name.as_str() {
+        "obfs-local" | "simple-obfs" | "obfs" => {
+            insert_opt(&mut opts, "mode", plugin.opt("obfs"));
+            insert_opt(&mut opts, "host", plugin.opt("obfs-host"));
+            "obfs"
+        }
+        "v2ray-plugin" => {
+            opts.insert(
+                "mode".into(),
+                plugin.opt("mode").unwrap_or("websocket").into(),
+            );
+            if plugin.opts.iter().any(|(k, _)| k == "tls") {
+                opts.insert("tls".into(), true.into());
+            }
+            insert_opt(&mut opts, "host", plugin.opt("host"));
+            insert_opt(&mut opts, "path", plugin.opt("path"));
+            "v2ray-plugin"
+        }
+        other => {
+            for (key, value) in &plugin.opts {
+                opts.insert(key.as_str().into(), value.as_str().into());
+            }
+            other
+        }
+    };
+    proxy.insert("plugin".into(), name.into());
+    if !opts.is_empty() {
+        proxy.insert("plugin-opts".into(), Value::Mapping(opts));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // 覆盖各协议常见字段的示例节点，供各输出格式的测试共用
+    pub(crate) fn sample_nodes() -> Vec<Node> {
+        let tls = |sni: &str| Tls {
+            enabled: true,
+            sni: Some(sni.to_string()),
+            ..Default::default()
+        };
+        vec![
+            Node {
+                name: "SS".to_string(),
+                server: "ss.example.com".to_string(),
+                port: 8388,
+                protocol: Protocol::Shadowsocks {
+                    cipher: "aes-128-gcm".to_string(),
+                    password: "ss-pass".to_string(),
+                    plugin: Some(Plugin {
+                        name: "obfs-local".to_string(),
+                        opts: vec![
+                            ("obfs".to_string(), "http".to_string()),
+                            ("obfs-host".to_string(), "bing.com".to_string()),
+                        ],
+                    }),
+                },
+            },
+            Node {
+                name: "SSR".to_string(),
+                server: "ssr.example.com".to_string(),
+                port: 443,
+                protocol: Protocol::ShadowsocksR {
+                    cipher: "aes-256-cfb".to_string(),
+                    password: "ssr-pass".to_string(),
+                    protocol: "auth_aes128_md5".to_string(),
+                    protocol_param: None,
+                    obfs: "tls1.2_ticket_auth".to_string(),
+                    obfs_param: Some("cdn.example.com".to_string()),
+                },
+            },
+            Node {
+                name: "VMess".to_string(),
+                server: "vmess.example.com".to_string(),
+                port: 443,
+                protocol: Protocol::Vmess {
+                    uuid: "11111111-1111-1111-1111-111111111111".to_string(),
+                    alter_id: 0,
+                    cipher: "auto".to_string(),
+                    transport: Transport {
+                        network: Network::Ws,
+                        path: Some("/ws".to_string()),
+                        host: Some("cdn.example.com".to_string()),
+                        service_name: None,
+                    },
+                    tls: tls("vmess.example.com"),
+                },
+            },
+            Node {
+                name: "Trojan".to_string(),
+                server: "trojan.example.com".to_string(),
+                port: 443,
+                protocol: Protocol::Trojan {
+                    password: "trojan-pass".to_string(),
+                    transport: Transport {
+                        network: Network::Grpc,
+                        service_name: Some("gun".to_string()),
+                        ..Default::default()
+                    },
+                    tls: Tls {
+                        insecure: true,
+                        ..tls("trojan.example.com")
+                    },
+                },
+            },
+            Node {
+                name: "VLESS".to_string(),
+                server: "vless.example.com".to_string(),
+                port: 443,
+                protocol: Protocol::Vless {
+                    uuid: "22222222-2222-2222-2222-222222222222".to_string(),
+                    flow: Some("xtls-rprx-vision".to_string()),
+                    transport: Transport::default(),
+                    tls: Tls {
+                        fingerprint: Some("chrome".to_string()),
+                        reality: Some(Reality {
+                            public_key: "reality-public-key".to_string(),
+                            short_id: Some("0123abcd".to_string()),
+                        }),
+                        ..tls("www.example.com")
+                    },
+                },
+            },
+            Node {
+                name: "Hysteria2".to_string(),
+                server: "hy2.example.com".to_string(),
+                port: 8443,
+                protocol: Protocol::Hysteria2 {
+                    password: "hy2-pass".to_string(),
+                    obfs_password: Some("obfs-pass".to_string()),
+                    tls: Tls {
+                        alpn: vec!["h3".to_string()],
+                        ..tls("hy2.example.com")
+                    },
+                },
+            },
+        ]
+    }
+
+    #[test]
+    fn test_clash_round_trip() {
+        for node in sample_nodes() {
+            assert_eq!(
+                Node::from_clash(&node.to_clash()).unwrap(),
+                node,
+                "{}",
+                node.name
+            );
+        }
+        let proxy: Mapping =
+            serde_yaml::from_str("{ name: a, type: snell, server: a.example.com, port: 1 }")
+                .unwrap();
+        assert_eq!(Node::from_clash(&proxy).unwrap_err(), "不支持的协议 snell");
+    }
+
+    #[test]
+    fn test_vless_reality_to_clash() {
+        let node = Node {
+            name: "JP Reality".to_string(),
+            server: "jp.example.com".to_string(),
+            port: 443,
+            protocol: Protocol::Vless {
+                uuid: "00000000-0000-0000-0000-000000000000".to_string(),
+                flow: Some("xtls-rprx-vision".to_string()),
+                transport: Transport::default(),
+                tls: Tls {
+                    enabled: true,
+                    sni: Some("www.example.com".to_string()),
+                    fingerprint: Some("chrome".to_string()),
+                    reality: Some(Reality {
+                        public_key: "pbk".to_string(),
+                        short_id: Some("abcd".to_string()),
+                    }),
+                    ..Default::default()
+                },
+            },
+        };
+        let proxy = Value::Mapping(node.to_clash());
+        assert_eq!(proxy["type"].as_str(), Some("vless"));
+        assert_eq!(proxy["tls"].as_bool(), Some(true));
+        assert_eq!(proxy["servername"].as_str(), Some("www.example.com"));
+        assert_eq!(proxy["reality-opts"]["public-key"].as_str(), Some("pbk"));
+        assert_eq!(proxy["client-fingerprint"].as_str(), Some("chrome"));
+        assert!(proxy.get("network").is_none());
+    }
+
+    #[test]
+    fn test_ss_plugin_to_clash() {
+        let node = Node {
+            name: "SS".to_string(),
+            server: "a.example.com".to_string(),
+            port: 8388,
+            protocol: Protocol::Shadowsocks {
+                cipher: "aes-128-gcm".to_string(),
+                password: "pw".to_string(),
+                plugin: Some(Plugin {
+                    name: "obfs-local".to_string(),
+                    opts: vec![
+                        ("obfs".to_string(), "http".to_string()),
+                        ("obfs-host".to_string(), "bing.com".to_string()),
+                    ],
+                }),
+            },
+        };
+        let proxy = Value::Mapping(node.to_clash());
+        assert_eq!(proxy["plugin"].as_str(), Some("obfs"));
+        assert_eq!(proxy["plugin-opts"]["mode"].as_str(), Some("http"));
+        assert_eq!(proxy["plugin-opts"]["host"].as_str(), Some("bing.com"));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::proxy::clash;
+use crate::proxy::node::Node;
+use crate::proxy::{quanx, singbox, surge, uri};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
+
+// 订阅输出的客户端格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    Clash,
+    Singbox,
+    Surge,
+    Quanx,
+    Base64, // 分享链接列表，v2rayN、Shadowrocket 等通用
+}
+
+impl Target {
+    // 需要从 Clash 配置转换的格式
+    pub const CONVERTED: [Target; 4] = [
+        Target::Singbox,
+        Target::Surge,
+        Target::Quanx,
+        Target::Base64,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "clash" | "mihomo" => Some(Target::Clash),
+            "singbox" | "sing-box" => Some(Target::Singbox),
+            "surge" => Some(Target::Surge),
+            "quanx" | "quantumultx" => Some(Target::Quanx),
+            "base64" | "v2ray" => Some(Target::Base64),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Target::Clash => "text/yaml; charset=utf-8",
+            Target::Singbox => "application/json; charset=utf-8",
+            Target::Surge | Target::Quanx | Target::Base64 => "text/plain; charset=utf-8",
+        }
+    }
+
+    // 下载文件名的扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Target::Clash => "yaml",
+            Target::Singbox => "json",
+            Target::Surge => "conf",
+            Target::Quanx | Target::Base64 => "txt",
+        }
+    }
+}
+
+// 目标格式无法表示而被跳过的节点
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedNode {
+    pub name: String,
+    pub reason: String,
+}
+
+pub struct Rendered {
+    pub content: String,
+    pub skipped: Vec<SkippedNode>,
+}
+
+// 把链接组生成的 Clash 配置转换为目标格式
+pub fn render(target: Target, clash_content: &str) -> Result<Rendered, serde_yaml::Error> {
+    let render_nodes: fn(&[Node]) -> Rendered = match target {
+        // 链接组本身就是 Clash 配置，原样输出
+        Target::Clash => {
+            return Ok(Rendered {
+                content: clash_content.to_string(),
+                skipped: Vec::new(),
+            });
+        }
+        Target::Singbox => singbox::render,
+        Target::Surge => surge::render,
+        Target::Quanx => quanx::render,
+        Target::Base64 => uri::render,
+    };
+
+    let mut nodes = Vec::new();
+    let mut skipped = Vec::new();
+    for proxy in clash::parse_proxies(clash_content)? {
+        match Node::from_clash(&proxy) {
+            Ok(node) => nodes.push(node),
+            Err(reason) => skipped.push(SkippedNode {
+                name: clash::proxy_name(&proxy).unwrap_or_default().to_string(),
+                reason,
+            }),
+        }
+    }
+
+    let mut rendered = render_nodes(&nodes);
+    skipped.append(&mut rendered.skipped);
+    rendered.skipped = skipped;
+    Ok(rendered)
+}
+
+// 检查节点能否以目标格式输出，不能时返回原因
+pub fn check(target: Target, proxy: &Mapping) -> Result<(), String> {
+    let check_node: fn(&Node) -> Result<(), String> = match target {
+        Target::Clash => return Ok(()),
+        Target::Singbox => |node| singbox::outbound(node, &node.name).map(drop),
+        Target::Surge => |node| surge::proxy_line(node, &node.name).map(drop),
+        Target::Quanx => |node| quanx::server_line(node, &node.name).map(drop),
+        Target::Base64 => |_| Ok(()),
+    };
+    check_node(&Node::from_clash(proxy)?)
+}
+
+// 逐行格式用逗号与等号分隔参数，名称中的这两个字符换成全角
+pub fn plain_name(name: &str) -> String {
+    name.replace(',', "，").replace('=', "＝")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = r#"
+proxies:
+  - { name: "SS", type: ss, server: a.example.com, port: 443, cipher: aes-128-gcm, password: pw }
+  - { name: "SSR", type: ssr, server: b.example.com, port: 443, cipher: aes-256-cfb, password: pw, protocol: origin, obfs: plain }
+  - { name: "Snell", type: snell, server: c.example.com, port: 443, psk: pw }
+"#;
+
+    #[test]
+    fn test_render_reports_skipped_nodes() {
+        assert_eq!(render(Target::Clash, CONTENT).unwrap().content, CONTENT);
+
+        let rendered = render(Target::Singbox, CONTENT).unwrap();
+        let names: Vec<&str> = rendered
+            .skipped
+            .iter()
+            .map(|node| node.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Snell", "SSR"]);
+        assert!(rendered.content.contains("a.example.com"));
+
+        assert_eq!(Target::parse("sing-box"), Some(Target::Singbox));
+        assert_eq!(Target::parse("QuanX"), Some(Target::Quanx));
+        assert_eq!(Target::parse("clash.meta"), None);
+    }
+
+    #[test]
+    fn test_check() {
+        let proxies = clash::parse_proxies(CONTENT).unwrap();
+        assert!(check(Target::Clash, &proxies[2]).is_ok());
+        assert!(check(Target::Surge, &proxies[0]).is_ok());
+        assert_eq!(
+            check(Target::Surge, &proxies[1]).unwrap_err(),
+            "Surge 不支持 ShadowsocksR"
+        );
+        assert!(check(Target::Quanx, &proxies[1]).is_ok());
+        assert_eq!(
+            check(Target::Quanx, &proxies[2]).unwrap_err(),
+            "不支持的协议 snell"
+        );
+    }
+}
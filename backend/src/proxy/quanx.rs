@@ -0,0 +1,242 @@
+use crate::proxy::clash::NameDeduper;
+use crate::proxy::node::{Network, Node, Protocol, Tls, Transport};
+use crate::proxy::output::{self, Rendered, SkippedNode};
+
+// 生成 Quantumult X 的 server_local 节点行，可直接作为 server_remote 资源引用
+pub fn render(nodes: &[Node]) -> Rendered {
+    let mut deduper = NameDeduper::with_reserved([]);
+    let mut content = String::new();
+    let mut skipped = Vec::new();
+    for node in nodes {
+        let tag = deduper.unique(&output::plain_name(&node.name));
+        match server_line(node, &tag) {
+            Ok(line) => {
+                content.push_str(&line);
+                content.push('\n');
+            }
+            Err(reason) => skipped.push(SkippedNode {
+                name: node.name.clone(),
+                reason,
+            }),
+        }
+    }
+    Rendered { content, skipped }
+}
+
+// 生成一行节点配置，Quantumult X 无法表示的节点返回原因
+pub fn server_line(node: &Node, tag: &str) -> Result<String, String> {
+    let mut params = Vec::new();
+    let kind = match &node.protocol {
+        Protocol::Shadowsocks {
+            cipher,
+            password,
+            plugin,
+        } => {
+            params.push(format!("method={cipher}"));
+            params.push(format!("password={password}"));
+            if let Some(plugin) = plugin {
+                let opt = |key: &str| {
+                    plugin
+                        .opts
+                        .iter()
+                        .find(|(k, _)| k == key)
+                        .map(|(_, v)| v.as_str())
+                };
+                match plugin.name.as_str() {
+                    "obfs-local" => {
+                        params.push(format!("obfs={}", opt("obfs").unwrap_or("http")));
+                        if let Some(host) = opt("obfs-host") {
+                            params.push(format!("obfs-host={host}"));
+                        }
+                    }
+                    "v2ray-plugin" if opt("mode").is_none_or(|mode| mode == "websocket") => {
+                        let obfs = if opt("tls").is_some() { "wss" } else { "ws" };
+                        params.push(format!("obfs={obfs}"));
+                        if let Some(host) = opt("host") {
+                            params.push(format!("obfs-host={host}"));
+                        }
+                        if let Some(path) = opt("path") {
+                            params.push(format!("obfs-uri={path}"));
+                        }
+                    }
+                    name => return Err(format!("Quantumult X 不支持插件 {name}")),
+                }
+            }
+            params.push("udp-relay=true".to_string());
+            "shadowsocks"
+        }
+        Protocol::ShadowsocksR {
+            cipher,
+            password,
+            protocol,
+            protocol_param,
+            obfs,
+            obfs_param,
+        } => {
+            params.push(format!("method={cipher}"));
+            params.push(format!("password={password}"));
+            params.push(format!("ssr-protocol={protocol}"));
+            if let Some(protocol_param) = protocol_param {
+                params.push(format!("ssr-protocol-param={protocol_param}"));
+            }
+            params.push(format!("obfs={obfs}"));
+            if let Some(obfs_param) = obfs_param {
+                params.push(format!("obfs-host={obfs_param}"));
+            }
+            "shadowsocks"
+        }
+        Protocol::Vmess {
+            uuid,
+            alter_id,
+            cipher,
+            transport,
+            tls,
+        } => {
+            let method = match cipher.as_str() {
+                "auto" | "" => "chacha20-poly1305",
+                cipher => cipher,
+            };
+            params.push(format!("method={method}"));
+            params.push(format!("password={uuid}"));
+            push_obfs(&mut params, transport, tls, false)?;
+            if *alter_id > 0 {
+                params.push("aead=false".to_string());
+            }
+            "vmess"
+        }
+        Protocol::Trojan {
+            password,
+            transport,
+            tls,
+        } => {
+            params.push(format!("password={password}"));
+            let tls = Tls {
+                enabled: true,
+                ..tls.clone()
+            };
+            push_obfs(&mut params, transport, &tls, true)?;
+            "trojan"
+        }
+        Protocol::Vless {
+            uuid,
+            flow,
+            transport,
+            tls,
+        } => {
+            params.push("method=none".to_string());
+            params.push(format!("password={uuid}"));
+            push_obfs(&mut params, transport, tls, false)?;
+            if let Some(reality) = &tls.reality {
+                params.push(format!("reality-base64-pubkey={}", reality.public_key));
+                if let Some(short_id) = &reality.short_id {
+                    params.push(format!("reality-hex-shortid={short_id}"));
+                }
+            }
+            if let Some(flow) = flow {
+                params.push(format!("vless-flow={flow}"));
+            }
+            "vless"
+        }
+        Protocol::Hysteria2 { .. } => return Err("Quantumult X 不支持 Hysteria2".to_string()),
+    };
+
+    let mut line = format!("{kind}={}:{}", node.server, node.port);
+    for item in params {
+        line.push_str(", ");
+        line.push_str(&item);
+    }
+    line.push_str(&format!(", tag={tag}"));
+    Ok(line)
+}
+
+// 传输层与 TLS 统一写成 obfs 参数；Trojan 走 TCP 时使用单独的 over-tls 写法
+fn push_obfs(
+    params: &mut Vec<String>,
+    transport: &Transport,
+    tls: &Tls,
+    trojan: bool,
+) -> Result<(), String> {
+    if tls.reality.is_some() && trojan {
+        return Err("Quantumult X 不支持 Trojan 的 REALITY".to_string());
+    }
+    match transport.network {
+        Network::Tcp if tls.enabled && trojan => {
+            params.push("over-tls=true".to_string());
+            if let Some(sni) = &tls.sni {
+                params.push(format!("tls-host={sni}"));
+            }
+        }
+        Network::Tcp if tls.enabled => {
+            params.push("obfs=over-tls".to_string());
+            if let Some(sni) = &tls.sni {
+                params.push(format!("obfs-host={sni}"));
+            }
+        }
+        Network::Tcp => {}
+        Network::Ws => {
+            let obfs = if tls.enabled { "wss" } else { "ws" };
+            params.push(format!("obfs={obfs}"));
+            if let Some(host) = transport.host.as_ref().or(tls.sni.as_ref()) {
+                params.push(format!("obfs-host={host}"));
+            }
+            if let Some(path) = &transport.path {
+                params.push(format!("obfs-uri={path}"));
+            }
+        }
+        network => return Err(format!("Quantumult X 不支持 {} 传输", network.as_str())),
+    }
+    if tls.enabled && tls.insecure {
+        params.push("tls-verification=false".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::tests::sample_nodes;
+
+    #[test]
+    fn test_render() {
+        let rendered = render(&sample_nodes());
+        let skipped: Vec<(&str, &str)> = rendered
+            .skipped
+            .iter()
+            .map(|node| (node.name.as_str(), node.reason.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("Trojan", "Quantumult X 不支持 grpc 传输"),
+                ("Hysteria2", "Quantumult X 不支持 Hysteria2"),
+            ]
+        );
+        let lines: Vec<&str> = rendered.content.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "shadowsocks=ss.example.com:8388, method=aes-128-gcm, password=ss-pass, obfs=http, obfs-host=bing.com, udp-relay=true, tag=SS",
+                "shadowsocks=ssr.example.com:443, method=aes-256-cfb, password=ssr-pass, ssr-protocol=auth_aes128_md5, \
+                 obfs=tls1.2_ticket_auth, obfs-host=cdn.example.com, tag=SSR",
+                "vmess=vmess.example.com:443, method=chacha20-poly1305, password=11111111-1111-1111-1111-111111111111, \
+                 obfs=wss, obfs-host=cdn.example.com, obfs-uri=/ws, tag=VMess",
+                "vless=vless.example.com:443, method=none, password=22222222-2222-2222-2222-222222222222, obfs=over-tls, \
+                 obfs-host=www.example.com, reality-base64-pubkey=reality-public-key, reality-hex-shortid=0123abcd, \
+                 vless-flow=xtls-rprx-vision, tag=VLESS",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trojan_over_tls() {
+        let mut node = sample_nodes().remove(3);
+        if let Protocol::Trojan { transport, .. } = &mut node.protocol {
+            *transport = Transport::default();
+        }
+        assert_eq!(
+            server_line(&node, "Trojan").unwrap(),
+            "trojan=trojan.example.com:443, password=trojan-pass, over-tls=true, tls-host=trojan.example.com, \
+             tls-verification=false, tag=Trojan"
+        );
+    }
+}
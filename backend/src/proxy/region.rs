@@ -0,0 +1,225 @@
+use regex::Regex;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+// 内置地区词典中的一项
+#[derive(Debug)]
+pub struct Region {
+    pub code: &'static str,                 // ISO 3166-1 两位代码，用于生成旗帜
+    pub abbrs: &'static [&'static str],     // 英文缩写，须大写且两侧不能是字母，第一个用于显示
+    pub names: &'static [&'static str],     // 英文全称，不区分大小写，两侧不能是字母
+    pub cjk_names: &'static [&'static str], // 中文名称，直接按子串匹配
+}
+
+impl Region {
+    // 由两位代码换算出区域指示符组成的旗帜 emoji
+    pub fn flag(&self) -> String {
+        self.code
+            .chars()
+            .filter_map(|c| char::from_u32(0x1F1E6 + (c as u32).checked_sub('A' as u32)?))
+            .collect()
+    }
+
+    // 重命名时显示的缩写
+    pub fn label(&self) -> &'static str {
+        self.abbrs[0]
+    }
+}
+
+// 按常见程度排列，匹配位置相同时排在前面的优先
+// 英国不匹配 GB，以免与流量单位混淆
+pub const REGIONS: &[Region] = &[
+    Region {
+        code: "HK",
+        abbrs: &["HK", "HKG"],
+        names: &["Hong ?Kong"],
+        cjk_names: &["香港"],
+    },
+    Region {
+        code: "TW",
+        abbrs: &["TW", "TWN"],
+        names: &["Taiwan"],
+        cjk_names: &["台湾", "臺灣", "台北"],
+    },
+    Region {
+        code: "MO",
+        abbrs: &["MO", "MAC"],
+        names: &["Macao", "Macau"],
+        cjk_names: &["澳门", "澳門"],
+    },
+    Region {
+        code: "JP",
+        abbrs: &["JP", "JPN"],
+        names: &["Japan"],
+        cjk_names: &["日本", "东京", "大阪"],
+    },
+    Region {
+        code: "KR",
+        abbrs: &["KR", "KOR"],
+        names: &["Korea"],
+        cjk_names: &["韩国", "韓國", "首尔"],
+    },
+    Region {
+        code: "SG",
+        abbrs: &["SG", "SGP"],
+        names: &["Singapore"],
+        cjk_names: &["新加坡", "狮城"],
+    },
+    Region {
+        code: "US",
+        abbrs: &["US", "USA"],
+        names: &["United States", "America"],
+        cjk_names: &["美国", "美國", "洛杉矶", "硅谷"],
+    },
+    Region {
+        code: "GB",
+        abbrs: &["UK", "GBR"],
+        names: &["United Kingdom", "Britain", "England"],
+        cjk_names: &["英国", "英國", "伦敦"],
+    },
+    Region {
+        code: "DE",
+        abbrs: &["DE", "DEU"],
+        names: &["Germany"],
+        cjk_names: &["德国", "德國", "法兰克福"],
+    },
+    Region {
+        code: "FR",
+        abbrs: &["FR", "FRA"],
+        names: &["France"],
+        cjk_names: &["法国", "法國", "巴黎"],
+    },
+    Region {
+        code: "NL",
+        abbrs: &["NL", "NLD"],
+        names: &["Netherlands"],
+        cjk_names: &["荷兰", "荷蘭", "阿姆斯特丹"],
+    },
+    Region {
+        code: "RU",
+        abbrs: &["RU", "RUS"],
+        names: &["Russia"],
+        cjk_names: &["俄罗斯", "俄羅斯", "莫斯科"],
+    },
+    Region {
+        code: "CA",
+        abbrs: &["CA", "CAN"],
+        names: &["Canada"],
+        cjk_names: &["加拿大"],
+    },
+    Region {
+        code: "AU",
+        abbrs: &["AU", "AUS"],
+        names: &["Australia"],
+        cjk_names: &["澳大利亚", "澳洲", "悉尼"],
+    },
+    Region {
+        code: "IN",
+        abbrs: &["IN", "IND"],
+        names: &["India"],
+        cjk_names: &["印度"],
+    },
+    Region {
+        code: "TR",
+        abbrs: &["TR", "TUR"],
+        names: &["Turkey", "Türkiye"],
+        cjk_names: &["土耳其"],
+    },
+];
+
+// 每个地区编译为一个正则，ascii 与 cjk 两个分组分别对应需要边界与不需要边界的关键词
+static MATCHERS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    REGIONS
+        .iter()
+        .map(|region| {
+            let pattern = format!(
+                "(?:^|[^A-Za-z])(?P<ascii>{}|(?i:{}))(?:[^A-Za-z]|$)|(?P<cjk>{})",
+                region.abbrs.join("|"),
+                region.names.join("|"),
+                region.cjk_names.join("|"),
+            );
+            Regex::new(&pattern).expect("Invalid region pattern")
+        })
+        .collect()
+});
+
+// 节点名称中识别出的地区
+#[derive(Debug)]
+pub struct RegionMatch {
+    pub region: &'static Region,
+    pub range: Range<usize>, // 命中关键词在名称中的字节范围
+}
+
+// 识别节点所属地区，多个地区同时出现时取最靠前的一个
+pub fn detect(name: &str) -> Option<RegionMatch> {
+    REGIONS
+        .iter()
+        .zip(MATCHERS.iter())
+        .filter_map(|(region, matcher)| {
+            let captures = matcher.captures(name)?;
+            let keyword = captures.name("ascii").or_else(|| captures.name("cjk"))?;
+            Some(RegionMatch {
+                region,
+                range: keyword.range(),
+            })
+        })
+        .min_by_key(|found| found.range.start)
+}
+
+// 去掉名称开头已有的旗帜 emoji
+pub fn strip_flag(name: &str) -> &str {
+    let is_indicator = |c: char| ('\u{1F1E6}'..='\u{1F1FF}').contains(&c);
+    name.trim_start_matches(is_indicator).trim_start()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_of(name: &str) -> Option<&'static str> {
+        detect(name).map(|found| found.region.code)
+    }
+
+    #[test]
+    fn test_dictionary_is_valid() {
+        for region in REGIONS {
+            assert_eq!(region.code.len(), 2);
+            assert!(!region.abbrs.is_empty());
+            assert_eq!(region.flag().chars().count(), 2);
+        }
+        assert_eq!(REGIONS[0].flag(), "🇭🇰");
+        // 强制编译全部正则
+        assert_eq!(MATCHERS.len(), REGIONS.len());
+    }
+
+    #[test]
+    fn test_detect_keywords() {
+        assert_eq!(code_of("香港 01"), Some("HK"));
+        assert_eq!(code_of("HK-IPLC-02"), Some("HK"));
+        assert_eq!(code_of("hong kong 03"), Some("HK"));
+        assert_eq!(code_of("HongKong 04"), Some("HK"));
+        assert_eq!(code_of("🇯🇵 东京 01"), Some("JP"));
+        assert_eq!(code_of("UK London"), Some("GB"));
+        assert_eq!(code_of("USA 01"), Some("US"));
+        // 缩写须独立出现，不能是单词的一部分
+        assert_eq!(code_of("SHKL 01"), None);
+        assert_eq!(code_of("Russian"), None);
+        assert_eq!(code_of("剩余流量：10 GB"), None);
+    }
+
+    #[test]
+    fn test_earliest_match_wins() {
+        let found = detect("日本 中转 香港").unwrap();
+        assert_eq!(found.region.code, "JP");
+        assert_eq!(&"日本 中转 香港"[found.range], "日本");
+
+        let found = detect("IPLC-HK-02").unwrap();
+        assert_eq!(&"IPLC-HK-02"[found.range], "HK");
+    }
+
+    #[test]
+    fn test_strip_flag() {
+        assert_eq!(strip_flag("🇭🇰 HK 01"), "HK 01");
+        assert_eq!(strip_flag("HK 01"), "HK 01");
+    }
+}
@@ -0,0 +1,153 @@
+use crate::proxy::region;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// 链接组的重命名规则，按顺序依次作用于节点名称
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RenameRule {
+    // 正则替换，replacement 中可以使用 $1、${name} 引用分组
+    Replace {
+        pattern: String,
+        #[serde(default)]
+        replacement: String,
+    },
+    // 以来源链接的名称作为前缀
+    Prefix {
+        #[serde(default = "default_separator")]
+        separator: String,
+    },
+    // 识别地区，把关键词统一为旗帜加代码，例如 "香港 01" → "🇭🇰 HK 01"
+    Region,
+}
+
+fn default_separator() -> String {
+    " | ".to_string()
+}
+
+enum Step {
+    Replace(Regex, String),
+    Prefix(String),
+    Region,
+}
+
+// 编译后的重命名规则
+pub struct Renamer {
+    steps: Vec<Step>,
+}
+
+impl Renamer {
+    pub fn new(rules: &[RenameRule]) -> Result<Self, regex::Error> {
+        let steps = rules
+            .iter()
+            .map(|rule| {
+                Ok(match rule {
+                    RenameRule::Replace {
+                        pattern,
+                        replacement,
+                    } => Step::Replace(Regex::new(pattern)?, replacement.clone()),
+                    RenameRule::Prefix { separator } => Step::Prefix(separator.clone()),
+                    RenameRule::Region => Step::Region,
+                })
+            })
+            .collect::<Result<_, regex::Error>>()?;
+        Ok(Self { steps })
+    }
+
+    // 计算节点的新名称，link_name 为节点来源链接的名称；结果为空时保留原名
+    pub fn apply(&self, name: &str, link_name: &str) -> String {
+        let mut current = name.to_string();
+        for step in &self.steps {
+            current = match step {
+                Step::Replace(re, replacement) => {
+                    re.replace_all(&current, replacement).into_owned()
+                }
+                Step::Prefix(separator) => format!("{link_name}{separator}{current}"),
+                Step::Region => rename_region(&current),
+            };
+        }
+
+        let current = current.trim();
+        if current.is_empty() {
+            name.to_string()
+        } else {
+            current.to_string()
+        }
+    }
+}
+
+fn rename_region(name: &str) -> String {
+    let name = region::strip_flag(name);
+    let Some(found) = region::detect(name) else {
+        return name.to_string();
+    };
+    let rest = format!("{}{}", &name[..found.range.start], &name[found.range.end..]);
+    let rest = rest.trim();
+    let label = format!("{} {}", found.region.flag(), found.region.label());
+    if rest.is_empty() {
+        label
+    } else {
+        format!("{label} {rest}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(json: &str) -> Vec<RenameRule> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_rule_format() {
+        let parsed = rules(
+            r#"[{"type":"replace","pattern":"\\s*x\\d+倍率"},{"type":"prefix"},{"type":"region"}]"#,
+        );
+        assert_eq!(
+            parsed,
+            vec![
+                RenameRule::Replace {
+                    pattern: r"\s*x\d+倍率".to_string(),
+                    replacement: String::new(),
+                },
+                RenameRule::Prefix {
+                    separator: " | ".to_string(),
+                },
+                RenameRule::Region,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_in_order() {
+        let renamer = Renamer::new(&rules(
+            r#"[{"type":"replace","pattern":"(?i)\\s*iplc","replacement":""},{"type":"region"},{"type":"prefix","separator":" - "}]"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            renamer.apply("香港 IPLC 01", "Airport"),
+            "Airport - 🇭🇰 HK 01"
+        );
+        assert_eq!(renamer.apply("Hong Kong", "Airport"), "Airport - 🇭🇰 HK");
+        assert_eq!(
+            renamer.apply("Unknown 01", "Airport"),
+            "Airport - Unknown 01"
+        );
+    }
+
+    #[test]
+    fn test_region_is_idempotent() {
+        let renamer = Renamer::new(&[RenameRule::Region]).unwrap();
+        let once = renamer.apply("JP 02 東京", "");
+        assert_eq!(once, "🇯🇵 JP 02 東京");
+        assert_eq!(renamer.apply(&once, ""), once);
+    }
+
+    #[test]
+    fn test_empty_result_keeps_original() {
+        let renamer = Renamer::new(&rules(r#"[{"type":"replace","pattern":".*"}]"#)).unwrap();
+        assert_eq!(renamer.apply("HK 01", ""), "HK 01");
+        assert!(Renamer::new(&rules(r#"[{"type":"replace","pattern":"("}]"#)).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use crate::proxy::clash::{AUTO_GROUP, NameDeduper, SELECT_GROUP, TEST_INTERVAL, TEST_URL};
+use crate::proxy::node::{Network, Node, Protocol, Tls, Transport};
+use crate::proxy::output::{Rendered, SkippedNode};
+use serde_json::{Map, Value, json};
+
+const DIRECT_TAG: &str = "direct";
+
+// 生成 sing-box 配置：节点 outbounds、选择与测速组，以及最小的路由设置
+pub fn render(nodes: &[Node]) -> Rendered {
+    let mut deduper = NameDeduper::with_reserved([SELECT_GROUP, AUTO_GROUP, DIRECT_TAG]);
+    let mut outbounds = Vec::new();
+    let mut tags = Vec::new();
+    let mut skipped = Vec::new();
+    for node in nodes {
+        let tag = deduper.unique(&node.name);
+        match outbound(node, &tag) {
+            Ok(outbound) => {
+                outbounds.push(outbound);
+                tags.push(tag);
+            }
+            Err(reason) => skipped.push(SkippedNode {
+                name: node.name.clone(),
+                reason,
+            }),
+        }
+    }
+
+    // 没有可用节点时测速组退化为直连，避免客户端拒绝加载
+    let auto_members = if tags.is_empty() {
+        vec![DIRECT_TAG.to_string()]
+    } else {
+        tags.clone()
+    };
+    let mut select_members = vec![AUTO_GROUP.to_string()];
+    select_members.extend(tags);
+    select_members.push(DIRECT_TAG.to_string());
+
+    let mut all = vec![
+        json!({
+            "type": "selector",
+            "tag": SELECT_GROUP,
+            "outbounds": select_members,
+            "default": AUTO_GROUP,
+        }),
+        json!({
+            "type": "urltest",
+            "tag": AUTO_GROUP,
+            "outbounds": auto_members,
+            "url": TEST_URL,
+            "interval": format!("{TEST_INTERVAL}s"),
+        }),
+    ];
+    all.append(&mut outbounds);
+    all.push(json!({ "type": "direct", "tag": DIRECT_TAG }));
+
+    let config = json!({
+        "outbounds": all,
+        "route": {
+            "rules": [{ "ip_is_private": true, "outbound": DIRECT_TAG }],
+            "final": SELECT_GROUP,
+            "auto_detect_interface": true,
+        },
+    });
+    Rendered {
+        content: serde_json::to_string_pretty(&config).expect("Config is serializable"),
+        skipped,
+    }
+}
+
+// 生成单个节点的 outbound，sing-box 无法表示的节点返回原因
+pub fn outbound(node: &Node, tag: &str) -> Result<Value, String> {
+    let mut out = Map::new();
+    let (kind, transport, tls) = match &node.protocol {
+        Protocol::Shadowsocks {
+            cipher,
+            password,
+            plugin,
+        } => {
+            out.insert("method".into(), cipher.as_str().into());
+            out.insert("password".into(), password.as_str().into());
+            if let Some(plugin) = plugin {
+                let opts: Vec<String> = plugin
+                    .opts
+                    .iter()
+                    .map(|(key, value)| {
+                        if value.is_empty() {
+                            key.clone()
+                        } else {
+                            format!("{key}={value}")
+                        }
+                    })
+                    .collect();
+                out.insert("plugin".into(), plugin.name.as_str().into());
+                out.insert("plugin_opts".into(), opts.join(";").into());
+            }
+            ("shadowsocks", None, None)
+        }
+        Protocol::ShadowsocksR { .. } => return Err("sing-box 不支持 ShadowsocksR".to_string()),
+        Protocol::Vmess {
+            uuid,
+            alter_id,
+            cipher,
+            transport,
+            tls,
+        } => {
+            out.insert("uuid".into(), uuid.as_str().into());
+            out.insert("security".into(), cipher.as_str().into());
+            out.insert("alter_id".into(), (*alter_id).into());
+            ("vmess", Some(transport), Some(tls.clone()))
+        }
+        Protocol::Trojan {
+            password,
+            transport,
+            tls,
+        } => {
+            out.insert("password".into(), password.as_str().into());
+            let tls = Tls {
+                enabled: true,
+                ..tls.clone()
+            };
+            ("trojan", Some(transport), Some(tls))
+        }
+        Protocol::Vless {
+            uuid,
+            flow,
+            transport,
+            tls,
+        } => {
+            out.insert("uuid".into(), uuid.as_str().into());
+            if let Some(flow) = flow {
+                out.insert("flow".into(), flow.as_str().into());
+            }
+            ("vless", Some(transport), Some(tls.clone()))
+        }
+        Protocol::Hysteria2 {
+            password,
+            obfs_password,
+            tls,
+        } => {
+            out.insert("password".into(), password.as_str().into());
+            if let Some(obfs_password) = obfs_password {
+                out.insert(
+                    "obfs".into(),
+                    json!({ "type": "salamander", "password": obfs_password }),
+                );
+            }
+            let tls = Tls {
+                enabled: true,
+                ..tls.clone()
+            };
+            ("hysteria2", None, Some(tls))
+        }
+    };
+
+    out.insert("type".into(), kind.into());
+    out.insert("tag".into(), tag.into());
+    out.insert("server".into(), node.server.as_str().into());
+    out.insert("server_port".into(), node.port.into());
+    if let Some(tls) = tls.filter(|tls| tls.enabled) {
+        out.insert("tls".into(), tls_options(&tls));
+    }
+    if let Some(transport) = transport.and_then(transport_options) {
+        out.insert("transport".into(), transport);
+    }
+    Ok(Value::Object(out))
+}
+
+fn tls_options(tls: &Tls) -> Value {
+    let mut out = Map::new();
+    out.insert("enabled".into(), true.into());
+    if let Some(sni) = &tls.sni {
+        out.insert("server_name".into(), sni.as_str().into());
+    }
+    if tls.insecure {
+        out.insert("insecure".into(), true.into());
+    }
+    if !tls.alpn.is_empty() {
+        out.insert("alpn".into(), tls.alpn.clone().into());
+    }
+    // REALITY 依赖 uTLS，未指定指纹时使用 chrome
+    let fingerprint = tls
+        .fingerprint
+        .as_deref()
+        .or(tls.reality.as_ref().map(|_| "chrome"));
+    if let Some(fingerprint) = fingerprint {
+        out.insert(
+            "utls".into(),
+            json!({ "enabled": true, "fingerprint": fingerprint }),
+        );
+    }
+    if let Some(reality) = &tls.reality {
+        let mut opts = Map::new();
+        opts.insert("enabled".into(), true.into());
+        opts.insert("public_key".into(), reality.public_key.as_str().into());
+        if let Some(short_id) = &reality.short_id {
+            opts.insert("short_id".into(), short_id.as_str().into());
+        }
+        out.insert("reality".into(), Value::Object(opts));
+    }
+    Value::Object(out)
+}
+
+fn transport_options(transport: &Transport) -> Option<Value> {
+    let mut out = Map::new();
+    match transport.network {
+        Network::Tcp => return None,
+        Network::Ws => {
+            out.insert("type".into(), "ws".into());
+            if let Some(path) = &transport.path {
+                out.insert("path".into(), path.as_str().into());
+            }
+            if let Some(host) = &transport.host {
+                out.insert("headers".into(), json!({ "Host": host }));
+            }
+        }
+        Network::Grpc => {
+            out.insert("type".into(), "grpc".into());
+            if let Some(service_name) = &transport.service_name {
+                out.insert("service_name".into(), service_name.as_str().into());
+            }
+        }
+        Network::H2 => {
+            out.insert("type".into(), "http".into());
+            if let Some(host) = &transport.host {
+                out.insert("host".into(), json!([host]));
+            }
+            if let Some(path) = &transport.path {
+                out.insert("path".into(), path.as_str().into());
+            }
+        }
+    }
+    Some(Value::Object(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::tests::sample_nodes;
+    use std::path::PathBuf;
+
+    // 与 testdata/singbox 下的期望输出比较；设置 UPDATE_GOLDEN=1 时改为重新生成
+    fn assert_golden(name: &str, actual: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/proxy/testdata/singbox")
+            .join(format!("{name}.json"));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, format!("{actual}\n")).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(actual, expected.trim_end(), "{}", path.display());
+    }
+
+    #[test]
+    fn test_golden_per_protocol() {
+        for node in sample_nodes() {
+            let rendered = render(std::slice::from_ref(&node));
+            if let Protocol::ShadowsocksR { .. } = node.protocol {
+                assert_eq!(rendered.skipped.len(), 1);
+                assert_golden("empty", &rendered.content);
+                continue;
+            }
+            assert!(rendered.skipped.is_empty());
+            let name = match node.protocol.kind() {
+                "ss" => "shadowsocks",
+                kind => kind,
+            };
+            assert_golden(name, &rendered.content);
+        }
+    }
+
+    #[test]
+    fn test_tags_are_unique() {
+        let mut nodes = sample_nodes();
+        nodes.truncate(1);
+        nodes.push(nodes[0].clone());
+        nodes[0].name = DIRECT_TAG.to_string();
+        let config: Value = serde_json::from_str(&render(&nodes).content).unwrap();
+        assert_eq!(
+            config["outbounds"][0]["outbounds"],
+            json!([AUTO_GROUP, "direct 2", "SS", DIRECT_TAG])
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use crate::proxy::clash::{AUTO_GROUP, NameDeduper, SELECT_GROUP, TEST_INTERVAL, TEST_URL};
+use crate::proxy::node::{Network, Node, Protocol, Tls, Transport};
+use crate::proxy::output::{self, Rendered, SkippedNode};
+
+// 生成 Surge 配置的 [Proxy]、[Proxy Group] 与兜底规则
+pub fn render(nodes: &[Node]) -> Rendered {
+    let mut deduper = NameDeduper::with_reserved([SELECT_GROUP, AUTO_GROUP]);
+    let mut lines = Vec::new();
+    let mut names = Vec::new();
+    let mut skipped = Vec::new();
+    for node in nodes {
+        let name = deduper.unique(&output::plain_name(&node.name));
+        match proxy_line(node, &name) {
+            Ok(line) => {
+                lines.push(line);
+                names.push(name);
+            }
+            Err(reason) => skipped.push(SkippedNode {
+                name: node.name.clone(),
+                reason,
+            }),
+        }
+    }
+
+    let mut select = vec![AUTO_GROUP, "DIRECT"];
+    select.extend(names.iter().map(String::as_str));
+    // url-test 组不能为空，没有节点时退化为直连
+    let auto = if names.is_empty() {
+        "DIRECT".to_string()
+    } else {
+        names.join(", ")
+    };
+
+    let mut content = String::from("[Proxy]\n");
+    for line in &lines {
+        content.push_str(line);
+        content.push('\n');
+    }
+    content.push_str("\n[Proxy Group]\n");
+    content.push_str(&format!("{SELECT_GROUP} = select, {}\n", select.join(", ")));
+    content.push_str(&format!(
+        "{AUTO_GROUP} = url-test, {auto}, url={TEST_URL}, interval={TEST_INTERVAL}\n"
+    ));
+    content.push_str(&format!("\n[Rule]\nFINAL,{SELECT_GROUP}\n"));
+    Rendered { content, skipped }
+}
+
+// 生成 [Proxy] 中的一行，Surge 无法表示的节点返回原因
+pub fn proxy_line(node: &Node, name: &str) -> Result<String, String> {
+    let mut params = Vec::new();
+    let kind = match &node.protocol {
+        Protocol::Shadowsocks {
+            cipher,
+            password,
+            plugin,
+        } => {
+            params.push(param("encrypt-method", cipher));
+            params.push(param("password", password));
+            if let Some(plugin) = plugin {
+                if plugin.name != "obfs-local" {
+                    return Err(format!("Surge 不支持插件 {}", plugin.name));
+                }
+                for (key, value) in &plugin.opts {
+                    if key == "obfs" || key == "obfs-host" {
+                        params.push(param(key, value));
+                    }
+                }
+            }
+            params.push("udp-relay=true".to_string());
+            "ss"
+        }
+        Protocol::ShadowsocksR { .. } => return Err("Surge 不支持 ShadowsocksR".to_string()),
+        Protocol::Vmess {
+            uuid,
+            alter_id,
+            transport,
+            tls,
+            ..
+        } => {
+            params.push(param("username", uuid));
+            if *alter_id == 0 {
+                params.push("vmess-aead=true".to_string());
+            }
+            push_tls(&mut params, tls, true)?;
+            push_transport(&mut params, transport)?;
+            "vmess"
+        }
+        Protocol::Trojan {
+            password,
+            transport,
+            tls,
+        } => {
+            params.push(param("password", password));
+            push_tls(&mut params, tls, false)?;
+            push_transport(&mut params, transport)?;
+            "trojan"
+        }
+        Protocol::Vless { .. } => return Err("Surge 不支持 VLESS".to_string()),
+        Protocol::Hysteria2 {
+            password,
+            obfs_password,
+            tls,
+        } => {
+            if obfs_password.is_some() {
+                return Err("Surge 不支持 Hysteria2 的 salamander 混淆".to_string());
+            }
+            params.push(param("password", password));
+            push_tls(&mut params, tls, false)?;
+            "hysteria2"
+        }
+    };
+
+    let mut line = format!("{name} = {kind}, {}, {}", node.server, node.port);
+    for item in params {
+        line.push_str(", ");
+        line.push_str(&item);
+    }
+    Ok(line)
+}
+
+// 值中含有逗号等分隔符时加上引号
+fn param(key: &str, value: &str) -> String {
+    if value.contains([',', '"', '=']) {
+        format!("{key}=\"{}\"", value.replace('"', "\\\""))
+    } else {
+        format!("{key}={value}")
+    }
+}
+
+// Trojan 与 Hysteria2 总是使用 TLS，只有 VMess 需要写出 tls 开关
+fn push_tls(params: &mut Vec<String>, tls: &Tls, with_flag: bool) -> Result<(), String> {
+    if tls.reality.is_some() {
+        return Err("Surge 不支持 REALITY".to_string());
+    }
+    if with_flag {
+        if !tls.enabled {
+            return Ok(());
+        }
+        params.push("tls=true".to_string());
+    }
+    if let Some(sni) = &tls.sni {
+        params.push(param("sni", sni));
+    }
+    if tls.insecure {
+        params.push("skip-cert-verify=true".to_string());
+    }
+    Ok(())
+}
+
+fn push_transport(params: &mut Vec<String>, transport: &Transport) -> Result<(), String> {
+    match transport.network {
+        Network::Tcp => {}
+        Network::Ws => {
+            params.push("ws=true".to_string());
+            if let Some(path) = &transport.path {
+                params.push(param("ws-path", path));
+            }
+            if let Some(host) = &transport.host {
+                params.push(param("ws-headers", &format!("Host:{host}")));
+            }
+        }
+        network => return Err(format!("Surge 不支持 {} 传输", network.as_str())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::node::tests::sample_nodes;
+
+    #[test]
+    fn test_render() {
+        let mut nodes = sample_nodes();
+        // 名称中的逗号会破坏 Surge 的语法
+        nodes[0].name = "SS,01".to_string();
+        let rendered = render(&nodes);
+
+        let skipped: Vec<(&str, &str)> = rendered
+            .skipped
+            .iter()
+            .map(|node| (node.name.as_str(), node.reason.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("SSR", "Surge 不支持 ShadowsocksR"),
+                ("Trojan", "Surge 不支持 grpc 传输"),
+                ("VLESS", "Surge 不支持 VLESS"),
+                ("Hysteria2", "Surge 不支持 Hysteria2 的 salamander 混淆"),
+            ]
+        );
+        assert_eq!(
+            rendered.content,
+            "[Proxy]\n\
+             SS，01 = ss, ss.example.com, 8388, encrypt-method=aes-128-gcm, password=ss-pass, obfs=http, obfs-host=bing.com, udp-relay=true\n\
+             VMess = vmess, vmess.example.com, 443, username=11111111-1111-1111-1111-111111111111, vmess-aead=true, tls=true, \
+             sni=vmess.example.com, ws=true, ws-path=/ws, ws-headers=Host:cdn.example.com\n\
+             \n\
+             [Proxy Group]\n\
+             节点选择 = select, 自动选择, DIRECT, SS，01, VMess\n\
+             自动选择 = url-test, SS，01, VMess, url=http://www.gstatic.com/generate_204, interval=300\n\
+             \n\
+             [Rule]\n\
+             FINAL,节点选择\n"
+        );
+    }
+
+    #[test]
+    fn test_trojan_and_hysteria2() {
+        let mut nodes = sample_nodes();
+        if let Protocol::Trojan { transport, .. } = &mut nodes[3].protocol {
+            *transport = Transport::default();
+        }
+        if let Protocol::Hysteria2 { obfs_password, .. } = &mut nodes[5].protocol {
+            *obfs_password = None;
+        }
+        assert_eq!(
+            proxy_line(&nodes[3], "Trojan").unwrap(),
+            "Trojan = trojan, trojan.example.com, 443, password=trojan-pass, sni=trojan.example.com, skip-cert-verify=true"
+        );
+        assert_eq!(
+            proxy_line(&nodes[5], "Hy2").unwrap(),
+            "Hy2 = hysteria2, hy2.example.com, 8443, password=hy2-pass, sni=hy2.example.com"
+        );
+    }
+}
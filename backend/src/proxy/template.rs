@@ -0,0 +1,294 @@
+use crate::proxy::clash::{self, NameDeduper};
+use regex::Regex;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+use std::fmt;
+
+// 策略组 proxies 列表中可用的占位符
+const ALL_NODES: &str = "$all"; // 全部节点
+const REGEX_PREFIX: &str = "$regex:"; // 名称匹配正则的节点，例如 $regex:香港|HK
+const LINK_PREFIX: &str = "$link:"; // 来自指定链接的节点，按链接 id 或 slug 匹配
+
+// 模板错误
+#[derive(Debug)]
+pub enum TemplateError {
+    Yaml(serde_yaml::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Yaml(e) => write!(f, "模板不是有效的 YAML：{e}"),
+            TemplateError::Invalid(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+enum Placeholder {
+    All,
+    Regex(Regex),
+    Link(String),
+}
+
+impl Placeholder {
+    // 解析策略组中的一项，普通的节点或策略组名称返回 None
+    fn parse(item: &str) -> Result<Option<Self>, TemplateError> {
+        if !item.starts_with('$') {
+            return Ok(None);
+        }
+        if item == ALL_NODES {
+            return Ok(Some(Placeholder::All));
+        }
+        if let Some(pattern) = item.strip_prefix(REGEX_PREFIX) {
+            let re = Regex::new(pattern).map_err(|e| {
+                TemplateError::Invalid(format!("占位符 {item} 不是有效的正则表达式：{e}"))
+            })?;
+            return Ok(Some(Placeholder::Regex(re)));
+        }
+        if let Some(link) = item
+            .strip_prefix(LINK_PREFIX)
+            .filter(|link| !link.is_empty())
+        {
+            return Ok(Some(Placeholder::Link(link.to_string())));
+        }
+        Err(TemplateError::Invalid(format!("未知的占位符 {item}")))
+    }
+
+    fn matches(&self, node: &TemplateNode, name: &str) -> bool {
+        match self {
+            Placeholder::All => true,
+            Placeholder::Regex(re) => re.is_match(name),
+            Placeholder::Link(link) => {
+                node.link_id.to_string() == *link
+                    || node.link_slug.as_deref() == Some(link.as_str())
+            }
+        }
+    }
+}
+
+// 注入模板的节点
+pub struct TemplateNode {
+    pub link_id: i64,
+    pub link_slug: Option<String>,
+    pub proxy: Mapping,
+}
+
+// 解析并校验模板：顶层必须是映射，proxy-groups 中的每个策略组都要有名称，占位符必须合法
+pub fn parse(template: &str) -> Result<Mapping, TemplateError> {
+    let config: Mapping = serde_yaml::from_str(template).map_err(TemplateError::Yaml)?;
+    for group in proxy_groups(&config)? {
+        let name = group
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| TemplateError::Invalid("策略组缺少名称".to_string()))?;
+        for item in group_items(group) {
+            Placeholder::parse(item)
+                .map_err(|e| TemplateError::Invalid(format!("策略组 {name}：{e}")))?;
+        }
+    }
+    Ok(config)
+}
+
+fn proxy_groups(config: &Mapping) -> Result<Vec<&Mapping>, TemplateError> {
+    match config.get("proxy-groups") {
+        None => Ok(Vec::new()),
+        Some(Value::Sequence(groups)) => groups
+            .iter()
+            .map(|group| {
+                group.as_mapping().ok_or_else(|| {
+                    TemplateError::Invalid("proxy-groups 的每一项都必须是映射".to_string())
+                })
+            })
+            .collect(),
+        Some(_) => Err(TemplateError::Invalid(
+            "proxy-groups 必须是列表".to_string(),
+        )),
+    }
+}
+
+fn group_items(group: &Mapping) -> impl Iterator<Item = &str> {
+    group
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+// 把节点注入模板：节点追加到 proxies，策略组中的占位符展开为对应的节点名称
+pub fn render(template: &str, nodes: Vec<TemplateNode>) -> Result<String, TemplateError> {
+    let mut config = parse(template)?;
+
+    // 模板自带的节点与策略组名称优先，注入的节点重名时追加后缀
+    let template_proxies: Vec<Value> = match config.get("proxies") {
+        Some(Value::Sequence(proxies)) => proxies.clone(),
+        _ => Vec::new(),
+    };
+    let mut reserved: Vec<String> = template_proxies
+        .iter()
+        .filter_map(|proxy| proxy.get("name").and_then(Value::as_str).map(String::from))
+        .collect();
+    reserved.extend(
+        proxy_groups(&config)?
+            .into_iter()
+            .filter_map(|group| group.get("name").and_then(Value::as_str).map(String::from)),
+    );
+    let mut deduper = NameDeduper::with_reserved(reserved.iter().map(String::as_str));
+
+    let mut names = Vec::with_capacity(nodes.len());
+    let mut proxies = template_proxies;
+    for mut node in nodes {
+        let Some(name) = clash::proxy_name(&node.proxy) else {
+            continue;
+        };
+        let name = deduper.unique(name);
+        clash::set_proxy_name(&mut node.proxy, &name);
+        proxies.push(Value::Mapping(node.proxy.clone()));
+        names.push((node, name));
+    }
+
+    if let Some(Value::Sequence(groups)) = config.get_mut("proxy-groups") {
+        for group in groups.iter_mut().filter_map(Value::as_mapping_mut) {
+            let Some(Value::Sequence(items)) = group.get_mut("proxies") else {
+                continue;
+            };
+            *items = expand_items(items, &names)?;
+        }
+    }
+    config.insert("proxies".into(), Value::Sequence(proxies));
+
+    serde_yaml::to_string(&config).map_err(TemplateError::Yaml)
+}
+
+// 展开一个策略组的成员，去掉重复项；展开后为空时退化为直连，避免客户端报错
+fn expand_items(
+    items: &[Value],
+    nodes: &[(TemplateNode, String)],
+) -> Result<Vec<Value>, TemplateError> {
+    let mut seen = HashSet::new();
+    let mut expanded = Vec::new();
+    for item in items {
+        let placeholder = match item.as_str() {
+            Some(text) => Placeholder::parse(text)?,
+            None => None,
+        };
+        let Some(placeholder) = placeholder else {
+            if item
+                .as_str()
+                .is_none_or(|text| seen.insert(text.to_string()))
+            {
+                expanded.push(item.clone());
+            }
+            continue;
+        };
+        for (node, name) in nodes {
+            if placeholder.matches(node, name) && seen.insert(name.clone()) {
+                expanded.push(Value::from(name.as_str()));
+            }
+        }
+    }
+    if expanded.is_empty() {
+        expanded.push(Value::from("DIRECT"));
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = r#"
+mixed-port: 7890
+dns:
+  enable: true
+  nameserver: [223.5.5.5]
+proxies:
+  - { name: "Home", type: socks5, server: 192.168.1.2, port: 1080 }
+proxy-groups:
+  - { name: "Proxy", type: select, proxies: ["Auto", "$all", "Home", "DIRECT"] }
+  - { name: "Auto", type: url-test, url: "http://www.gstatic.com/generate_204", interval: 300, proxies: ["$regex:(?i)hk|香港"] }
+  - { name: "Backup", type: select, proxies: ["$link:backup", "$link:2"] }
+  - { name: "Empty", type: select, proxies: ["$regex:^US"] }
+rules:
+  - DOMAIN-SUFFIX,lan,DIRECT
+  - MATCH,Proxy
+"#;
+
+    fn node(link_id: i64, slug: Option<&str>, name: &str) -> TemplateNode {
+        TemplateNode {
+            link_id,
+            link_slug: slug.map(String::from),
+            proxy: serde_yaml::from_str(&format!(
+                "{{ name: \"{name}\", type: ss, server: a.example.com, port: 443 }}"
+            ))
+            .unwrap(),
+        }
+    }
+
+    fn group_members(config: &Value, index: usize) -> Vec<&str> {
+        config["proxy-groups"][index]["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|item| item.as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_render_expands_placeholders() {
+        let nodes = vec![
+            node(1, Some("main"), "HK 01"),
+            node(1, Some("main"), "JP 01"),
+            node(2, Some("backup"), "香港 02"),
+            node(2, Some("backup"), "Home"),
+        ];
+        let rendered = render(TEMPLATE, nodes).unwrap();
+        let config: Value = serde_yaml::from_str(&rendered).unwrap();
+
+        // 非占位符的配置原样保留
+        assert_eq!(config["dns"]["nameserver"][0].as_str(), Some("223.5.5.5"));
+        assert_eq!(config["rules"][1].as_str(), Some("MATCH,Proxy"));
+
+        // 与模板节点重名的注入节点追加后缀
+        let names: Vec<&str> = config["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|proxy| proxy["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Home", "HK 01", "JP 01", "香港 02", "Home 2"]);
+
+        assert_eq!(
+            group_members(&config, 0),
+            vec![
+                "Auto",
+                "HK 01",
+                "JP 01",
+                "香港 02",
+                "Home 2",
+                "Home",
+                "DIRECT"
+            ]
+        );
+        assert_eq!(group_members(&config, 1), vec!["HK 01", "香港 02"]);
+        assert_eq!(group_members(&config, 2), vec!["香港 02", "Home 2"]);
+        assert_eq!(group_members(&config, 3), vec!["DIRECT"]);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_templates() {
+        assert!(parse("- a\n- b").is_err());
+        assert!(parse("proxy-groups: { name: a }").is_err());
+        assert!(parse("proxy-groups:\n  - { type: select, proxies: [DIRECT] }").is_err());
+        assert!(
+            parse("proxy-groups:\n  - { name: a, type: select, proxies: [\"$regex:(\"] }").is_err()
+        );
+        assert!(
+            parse("proxy-groups:\n  - { name: a, type: select, proxies: [\"$nodes\"] }").is_err()
+        );
+        assert!(parse("rules: [MATCH,DIRECT]").is_ok());
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+
+// 机场通过 Subscription-Userinfo 响应头下发的流量信息，单位为字节，expire 为 Unix 秒
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubscriptionUserinfo {
+    pub upload: u64,
+    pub download: u64,
+    pub total: u64,
+    pub expire: Option<u64>,
+}
+
+impl SubscriptionUserinfo {
+    // 解析形如 "upload=1; download=2; total=3; expire=4" 的头部值，无法识别的字段会被忽略
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut info = Self::default();
+        let mut found = false;
+        for pair in value.split(';') {
+            let Some((key, raw)) = pair.split_once('=') else {
+                continue;
+            };
+            let Some(number) = parse_number(raw.trim()) else {
+                continue;
+            };
+            match key.trim().to_ascii_lowercase().as_str() {
+                "upload" => info.upload = number,
+                "download" => info.download = number,
+                "total" => info.total = number,
+                // 部分机场用 0 表示不过期
+                "expire" => info.expire = (number > 0).then_some(number),
+                _ => continue,
+            }
+            found = true;
+        }
+        found.then_some(info)
+    }
+
+    // 汇总多个上游的流量：用量与总量相加，到期时间取最早的一个
+    pub fn aggregate<I>(infos: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        infos.into_iter().reduce(|acc, info| Self {
+            upload: acc.upload.saturating_add(info.upload),
+            download: acc.download.saturating_add(info.download),
+            total: acc.total.saturating_add(info.total),
+            expire: match (acc.expire, info.expire) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        })
+    }
+}
+
+// 个别机场会返回科学计数法或小数
+fn parse_number(raw: &str) -> Option<u64> {
+    raw.parse::<u64>().ok().or_else(|| {
+        raw.parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite() && *n >= 0.0)
+            .map(|n| n as u64)
+    })
+}
+
+impl fmt::Display for SubscriptionUserinfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "upload={}; download={}; total={}",
+            self.upload, self.download, self.total
+        )?;
+        if let Some(expire) = self.expire {
+            write!(f, "; expire={expire}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let info = SubscriptionUserinfo::parse(
+            "upload=100; download=2.5e3; total=10737418240; expire=1735689600",
+        )
+        .unwrap();
+        assert_eq!(info.upload, 100);
+        assert_eq!(info.download, 2500);
+        assert_eq!(info.total, 10737418240);
+        assert_eq!(info.expire, Some(1735689600));
+
+        let info = SubscriptionUserinfo::parse("upload=1;download=2;total=3;expire=").unwrap();
+        assert_eq!(info.expire, None);
+        assert_eq!(SubscriptionUserinfo::parse("garbage"), None);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let infos = [
+            "upload=1; download=2; total=100; expire=2000",
+            "upload=10; download=20; total=200; expire=1000",
+            "upload=5; download=5; total=50",
+        ]
+        .iter()
+        .filter_map(|value| SubscriptionUserinfo::parse(value));
+        let info = SubscriptionUserinfo::aggregate(infos).unwrap();
+        assert_eq!(
+            info.to_string(),
+            "upload=16; download=27; total=350; expire=1000"
+        );
+        assert_eq!(SubscriptionUserinfo::aggregate([]), None);
+    }
+}
@@ -1,37 +1,100 @@
+use crate::config::Config;
 use crate::db::DbClient;
-use crate::handlers::login;
-use crate::handlers::login::login;
+use crate::handlers::{convert, groups, links, login, subscription};
 use crate::middlewares::auth;
+use crate::services::access_log::AccessLogSweeper;
+use crate::services::fetcher::Fetcher;
+use crate::services::group_builder::GroupBuilder;
+use crate::services::refresher::CacheRefresher;
 use crate::types::app_state::AppState;
+use crate::types::login_limiter::LoginLimiter;
 use crate::types::session_store::SessionStore;
 use axum::{
     Router,
-    extract::{Extension, Form, Json, Path, Query, State},
     http::status::StatusCode,
     middleware,
-    routing::{get, post},
+    routing::{get, post, put},
 };
-use sqlx::SqlitePool;
-use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use tokio::sync::watch;
 
 #[tokio::main]
-async fn run() {
+async fn run(config: Config) {
     // 初始化数据结构
-    let db_client = DbClient::connect().await.unwrap();
-    let sessions = SessionStore::new();
+    let db_client = DbClient::connect(&config.database).await.unwrap();
+    let sessions = SessionStore::from_config(&config.session, db_client.clone());
+    let login_limiter = LoginLimiter::new(config.login.clone());
+    let fetcher = Fetcher::new(&config.fetcher);
+    let group_builder = GroupBuilder::new(db_client.clone(), fetcher.clone());
 
     // 创建应用状态
     let app_state = Arc::new(AppState {
         db_client,
         sessions,
+        login_limiter,
+        group_builder,
+        config,
     });
 
+    // 启动后台缓存刷新任务
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let refresher = CacheRefresher::new(
+        app_state.db_client.clone(),
+        fetcher,
+        &app_state.config.refresher,
+    )
+    .spawn(shutdown_rx.clone());
+    // 定期清理过期会话、登录失败记录与旧的拉取记录
+    let sweep_interval = app_state.config.session.sweep_interval();
+    let sweeper = app_state
+        .sessions
+        .spawn_sweeper(sweep_interval, shutdown_rx.clone());
+    let limiter_sweeper = app_state
+        .login_limiter
+        .spawn_sweeper(sweep_interval, shutdown_rx.clone());
+    let access_log_sweeper =
+        AccessLogSweeper::new(app_state.db_client.clone(), &app_state.config.access_log)
+            .spawn(sweep_interval, shutdown_rx);
+
     // 建立路由
 
     // 实时鉴权的路由
     let auth_routes = Router::new()
         .route("/api", get(|| async { "Hello, World!" }))
+        .route("/api/auth/logout", post(auth::logout))
+        .route("/api/auth/logout-all", post(auth::logout_all))
+        .route(
+            "/api/links",
+            get(links::list_links).post(links::create_link),
+        )
+        .route(
+            "/api/links/{id}",
+            get(links::get_link)
+                .put(links::update_link)
+                .delete(links::delete_link),
+        )
+        .route(
+            "/api/groups",
+            get(groups::list_groups).post(groups::create_group),
+        )
+        .route(
+            "/api/groups/{id}",
+            get(groups::get_group)
+                .put(groups::update_group)
+                .delete(groups::delete_group),
+        )
+        .route("/api/groups/{id}/access-log", get(groups::get_access_log))
+        .route("/api/groups/{id}/preview", get(groups::preview_group))
+        .route(
+            "/api/groups/{id}/links",
+            get(groups::list_group_links).put(groups::reorder_group_links),
+        )
+        .route(
+            "/api/groups/{id}/links/{link_id}",
+            put(groups::put_group_link).delete(groups::delete_group_link),
+        )
+        .route("/api/convert", get(convert::convert))
         .route_layer(middleware::from_fn_with_state(
             app_state.clone(),
             auth::auth_middleware,
@@ -41,6 +104,7 @@ async fn run() {
     // 不具备实时鉴权
     let other_routes = Router::new()
         .route("/api/auth/login", post(login::login))
+        .route("/sub/{slug}", get(subscription::get_group_sub))
         .with_state(app_state.clone());
 
     // 合并路由
@@ -49,18 +113,55 @@ async fn run() {
         .merge(other_routes)
         .fallback(|| async { StatusCode::NOT_FOUND });
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:30022")
+    let listener = tokio::net::TcpListener::bind(&app_state.config.server.bind)
         .await
         .unwrap();
     println!(
         "server started on http://{}",
         listener.local_addr().unwrap()
     );
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await
+    .unwrap();
+
+    // 通知后台任务退出并等待其结束
+    let _ = shutdown_tx.send(true);
+    let _ = refresher.await;
+    let _ = sweeper.await;
+    let _ = limiter_sweeper.await;
+    let _ = access_log_sweeper.await;
+}
+
+// 等待 Ctrl+C 或 SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
 
-pub fn main() {
-    run()
+pub fn main(config: Config) {
+    run(config)
 }
 
 #[cfg(test)]
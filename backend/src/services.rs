@@ -0,0 +1,4 @@
+pub(crate) mod access_log;
+pub(crate) mod fetcher;
+pub(crate) mod group_builder;
+pub(crate) mod refresher;
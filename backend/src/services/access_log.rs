@@ -0,0 +1,148 @@
+use crate::config::AccessLogConfig;
+use crate::db::DbClient;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+// 写入拉取记录时 User-Agent 保留的最大字符数
+const MAX_USER_AGENT_CHARS: usize = 256;
+
+// 订阅拉取记录的清理任务，按保留天数与每组条数上限删除旧记录
+pub struct AccessLogSweeper {
+    db_client: DbClient,
+    config: AccessLogConfig,
+}
+
+impl AccessLogSweeper {
+    pub fn new(db_client: DbClient, config: &AccessLogConfig) -> Self {
+        Self {
+            db_client,
+            config: config.clone(),
+        }
+    }
+
+    // 执行一次清理，返回删除的记录数
+    pub async fn purge_once(&self) -> u64 {
+        let result = self
+            .db_client
+            .delete_old_access_logs(
+                self.config.max_age().as_secs(),
+                self.config.max_rows_per_group,
+            )
+            .await;
+        match result {
+            Ok(purged) => purged,
+            Err(e) => {
+                log::error!("failed to purge access logs: {e}");
+                0
+            }
+        }
+    }
+
+    // 启动定期清理的后台任务，shutdown 收到信号后退出
+    pub fn spawn(self, period: Duration, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        let purged = self.purge_once().await;
+                        if purged > 0 {
+                            log::debug!("purged {purged} access logs");
+                        }
+                    }
+                    _ = shutdown.changed() => break,
+                }
+            }
+        })
+    }
+}
+
+// 截断过长的 User-Agent，避免客户端借此写入任意长度的数据
+pub fn truncate_user_agent(user_agent: &str) -> &str {
+    match user_agent.char_indices().nth(MAX_USER_AGENT_CHARS) {
+        Some((end, _)) => &user_agent[..end],
+        None => user_agent,
+    }
+}
+
+// 脱敏访问密钥：不少于 8 个字符时保留首尾各两个字符，否则全部隐藏
+// 与 0008 迁移中处理历史记录的规则一致
+pub fn mask_key(key: &str) -> Option<String> {
+    let chars: Vec<char> = key.chars().collect();
+    match chars.len() {
+        0 => None,
+        len if len >= 8 => {
+            let head: String = chars[..2].iter().collect();
+            let tail: String = chars[len - 2..].iter().collect();
+            Some(format!("{head}****{tail}"))
+        }
+        _ => Some("****".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_and_mask() {
+        assert_eq!(truncate_user_agent("clash.meta"), "clash.meta");
+        let long = "流".repeat(300);
+        assert_eq!(
+            truncate_user_agent(&long).chars().count(),
+            MAX_USER_AGENT_CHARS
+        );
+
+        assert_eq!(mask_key(""), None);
+        assert_eq!(mask_key("secret").as_deref(), Some("****"));
+        assert_eq!(mask_key("super-secret").as_deref(), Some("su****et"));
+    }
+
+    #[tokio::test]
+    async fn test_purge_caps_rows_per_group() {
+        // 清理会作用于所有链接组，使用独立的内存数据库
+        let db_client = DbClient::connect_in_memory().await.unwrap();
+        let user_id = db_client
+            .create_user("access-log-owner", "pw")
+            .await
+            .unwrap();
+        let group_id = db_client
+            .create_link_group(
+                user_id,
+                "Logs",
+                "access-log-test",
+                None,
+                None,
+                false,
+                None,
+                0,
+                &Default::default(),
+            )
+            .await
+            .unwrap();
+        for status in [200, 403, 200, 404, 200] {
+            db_client
+                .create_group_access_log(group_id, "10.0.0.1", None, None, status)
+                .await
+                .unwrap();
+        }
+
+        let config = AccessLogConfig {
+            max_age_days: 30,
+            max_rows_per_group: 3,
+        };
+        let sweeper = AccessLogSweeper::new(db_client.clone(), &config);
+        assert_eq!(sweeper.purge_once().await, 2);
+        let kept = db_client
+            .get_group_access_logs(group_id, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            kept.iter().map(|log| log.status).collect::<Vec<_>>(),
+            vec![200, 404, 200]
+        );
+
+        db_client.delete_user(user_id).await.unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+use crate::config::FetcherConfig;
+use reqwest::Client;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+#[cfg(test)]
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Host, Url};
+
+// 单次拉取的超时时间
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+// 响应体大小上限，订阅内容通常远小于该值
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 10;
+
+// 一次拉取的结果
+pub struct Fetched {
+    pub body: String,
+    pub userinfo: Option<String>, // 上游返回的 Subscription-Userinfo 头
+}
+
+// 拉取失败的原因
+#[derive(Debug)]
+pub enum FetchError {
+    Request(reqwest::Error),
+    Forbidden(String), // 目标不是公网地址
+    TooLarge,          // 响应体超过大小上限
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Request(e) => write!(f, "{e}"),
+            FetchError::Forbidden(host) => write!(f, "{host} is not a public address"),
+            FetchError::TooLarge => write!(f, "response body exceeds {MAX_BODY_BYTES} bytes"),
+        }
+    }
+}
+
+impl Error for FetchError {}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        // 解析器或重定向策略拦截的内网地址包装在请求错误中
+        let mut source = e.source();
+        while let Some(inner) = source {
+            if let Some(blocked) = inner.downcast_ref::<BlockedAddress>() {
+                return FetchError::Forbidden(blocked.0.clone());
+            }
+            source = inner.source();
+        }
+        FetchError::Request(e)
+    }
+}
+
+// 上游订阅拉取器
+#[derive(Clone)]
+pub enum Fetcher {
+    // 通过 HTTP 拉取，内部复用连接池；public 只允许连接公网地址，internal 仅在配置允许时存在
+    Http {
+        public: Client,
+        internal: Option<Client>,
+    },
+    // 对任意公网地址都返回固定内容，用于测试
+    #[cfg(test)]
+    Static(Arc<str>),
+}
+
+impl Fetcher {
+    pub fn new(config: &FetcherConfig) -> Self {
+        let internal = config.allow_internal_upstreams.then(|| {
+            Client::builder()
+                .timeout(FETCH_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client")
+        });
+        // 不经过系统代理，保证连接的正是解析器放行的地址
+        let public = Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .no_proxy()
+            .dns_resolver(PublicResolver)
+            .redirect(Policy::custom(|attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                match check_literal_host(attempt.url()) {
+                    Ok(()) => attempt.follow(),
+                    Err(blocked) => attempt.error(blocked),
+                }
+            }))
+            .build()
+            .expect("Failed to build HTTP client");
+        Fetcher::Http { public, internal }
+    }
+
+    // 拉取已保存链接的上游内容，按链接类型选择 User-Agent
+    // 与临时地址一样只允许公网地址，配置开启 allow_internal_upstreams 后才可以指向内网
+    pub async fn fetch(&self, url: &str, link_type: &str) -> Result<Fetched, FetchError> {
+        match self {
+            Fetcher::Http {
+                internal: Some(client),
+                ..
+            } => get(client, url, link_type).await,
+            _ => self.fetch_public(url, link_type).await,
+        }
+    }
+
+    // 拉取临时提交的地址，只允许访问公网地址
+    // 域名在连接时由解析器过滤，重定向目标同样受限
+    pub async fn fetch_public(&self, url: &str, link_type: &str) -> Result<Fetched, FetchError> {
+        if let Ok(parsed) = Url::parse(url) {
+            check_literal_host(&parsed).map_err(|blocked| FetchError::Forbidden(blocked.0))?;
+        }
+        match self {
+            Fetcher::Http { public, .. } => get(public, url, link_type).await,
+            #[cfg(test)]
+            Fetcher::Static(body) => Ok(Fetched {
+                body: body.to_string(),
+                userinfo: None,
+            }),
+        }
+    }
+}
+
+// 多数机场会根据 UA 决定返回 Clash 配置还是通用订阅
+async fn get(client: &Client, url: &str, link_type: &str) -> Result<Fetched, FetchError> {
+    let user_agent = match link_type {
+        "clash" => "clash.meta",
+        _ => concat!("IsekaiLink/", env!("CARGO_PKG_VERSION")),
+    };
+
+    let mut response = client
+        .get(url)
+        .header(reqwest::header::USER_AGENT, user_agent)
+        .send()
+        .await?
+        .error_for_status()?;
+    let userinfo = response
+        .headers()
+        .get("subscription-userinfo")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string());
+
+    // 分块读取，超过上限立即放弃，不信任上游声明的长度
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_BODY_BYTES as u64)
+    {
+        return Err(FetchError::TooLarge);
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(FetchError::TooLarge);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let body = String::from_utf8_lossy(&body).into_owned();
+    Ok(Fetched { body, userinfo })
+}
+
+// 被拦截的非公网地址
+#[derive(Debug)]
+struct BlockedAddress(String);
+
+impl fmt::Display for BlockedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not a public address", self.0)
+    }
+}
+
+impl Error for BlockedAddress {}
+
+// 只返回公网地址的 DNS 解析器，连接直接使用过滤后的结果，避免解析后被换成内网地址
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(BlockedAddress(host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+// 地址中直接写出的 IP 不经过解析器，需要单独检查
+fn check_literal_host(url: &Url) -> Result<(), BlockedAddress> {
+    let ip = match url.host() {
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+        _ => return Ok(()),
+    };
+    if is_public(ip) {
+        Ok(())
+    } else {
+        Err(BlockedAddress(ip.to_string()))
+    }
+}
+
+// 是否为公网地址：排除本机、私有、链路本地、运营商 NAT、组播与保留地址
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_v4(mapped);
+            }
+            let segments = ip.segments();
+            // NAT64 地址内嵌 IPv4
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public_v4(Ipv4Addr::new(a, b, c, d));
+            }
+            let documentation = segments[0] == 0x2001 && segments[1] == 0x0db8;
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                || documentation)
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    let shared = a == 100 && (64..128).contains(&b); // 100.64.0.0/10
+    let benchmarking = a == 198 && (b & 0xfe) == 18; // 198.18.0.0/15
+    !(a == 0
+        || a >= 240
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || shared
+        || benchmarking)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, routing::get as route_get};
+
+    #[test]
+    fn test_is_public() {
+        for ip in ["1.1.1.1", "8.8.8.8", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_public_rejects_internal_hosts() {
+        let fetcher = Fetcher::new(&FetcherConfig::default());
+        for url in [
+            "http://127.0.0.1:1/",
+            "http://[::1]:1/",
+            "http://localhost:1/",
+        ] {
+            let result = fetcher.fetch_public(url, "clash").await;
+            assert!(matches!(result, Err(FetchError::Forbidden(_))), "{url}");
+            // 已保存的链接默认同样受限
+            let result = fetcher.fetch(url, "clash").await;
+            assert!(matches!(result, Err(FetchError::Forbidden(_))), "{url}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_body_size_is_capped() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/small", route_get(|| async { "proxies: []" }))
+            .route(
+                "/large",
+                route_get(|| async { "#".repeat(MAX_BODY_BYTES + 1) }),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // 配置允许后已保存的链接可以指向本机
+        let fetcher = Fetcher::new(&FetcherConfig {
+            allow_internal_upstreams: true,
+        });
+        let fetched = fetcher
+            .fetch(&format!("http://{addr}/small"), "clash")
+            .await
+            .unwrap();
+        assert_eq!(fetched.body, "proxies: []");
+        let result = fetcher
+            .fetch(&format!("http://{addr}/large"), "clash")
+            .await;
+        assert!(matches!(result, Err(FetchError::TooLarge)));
+    }
+}
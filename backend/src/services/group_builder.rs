@@ -0,0 +1,329 @@
+use crate::db::{DbClient, GroupRules, Link, LinkGroup};
+use crate::proxy::clash;
+use crate::proxy::dedup::{self, DedupNode, DuplicateSet};
+use crate::proxy::filter::{FilterVerdict, NodeFilter};
+use crate::proxy::node::Node;
+use crate::proxy::output::{self, Target};
+use crate::proxy::rename::Renamer;
+use crate::proxy::template::{self, TemplateNode};
+use crate::proxy::uri::{self, Diagnostic};
+use crate::services::fetcher::{FetchError, Fetcher};
+use serde::Serialize;
+use serde_yaml::Mapping;
+use std::collections::HashMap;
+
+// 链接组生成结果的预览
+#[derive(Debug, Default, Serialize)]
+pub struct GroupPreview {
+    pub kept: Vec<PreviewNode>,
+    pub dropped: Vec<DroppedNode>,        // 被过滤规则丢弃的节点
+    pub duplicates: Vec<DuplicateSet>,    // 按去重策略合并的重复节点
+    pub diagnostics: Vec<LinkDiagnostic>, // 分享链接中无法解析的行
+    pub warnings: Vec<TargetWarning>,     // 输出为其他客户端格式时会被跳过的节点
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetWarning {
+    pub target: Target,
+    pub link_id: i64,
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkDiagnostic {
+    pub link_id: i64,
+    #[serde(flatten)]
+    pub diagnostic: Diagnostic,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewNode {
+    pub link_id: i64,
+    pub name: String,          // 重命名后的名称
+    pub original_name: String, // 上游提供的名称
+}
+
+#[derive(Debug, Serialize)]
+pub struct DroppedNode {
+    pub link_id: i64,
+    pub name: String,
+    #[serde(flatten)]
+    pub verdict: FilterVerdict,
+}
+
+// 处理后的组内节点，同时记录预览信息
+#[derive(Default)]
+struct Assembled {
+    nodes: Vec<TemplateNode>,
+    has_sources: bool,  // 是否至少有一个可用的链接
+    fetch_failed: bool, // 是否有链接拉取上游失败
+    preview: GroupPreview,
+}
+
+// 临时转换的结果
+pub struct Converted {
+    pub total: usize,    // 解析出的节点数
+    pub kept: usize,     // 过滤与去重后保留的节点数
+    pub content: String, // 合并后的 Clash 配置
+}
+
+// 链接组配置生成器，把组内各链接的节点合并为一份配置
+#[derive(Clone)]
+pub struct GroupBuilder {
+    db_client: DbClient,
+    fetcher: Fetcher,
+}
+
+impl GroupBuilder {
+    pub fn new(db_client: DbClient, fetcher: Fetcher) -> Self {
+        Self { db_client, fetcher }
+    }
+
+    // 生成链接组的合并配置，设置了模板时把节点注入模板；组内没有链接时生成空配置，
+    // 上游全部拉取失败时返回 None 以保留旧缓存
+    pub async fn build(&self, group_id: i64) -> Result<Option<String>, sqlx::Error> {
+        let group = self.db_client.get_link_group_by_id(group_id).await?;
+        let assembled = self.assemble_group(&group).await?;
+
+        if !assembled.has_sources && assembled.fetch_failed {
+            return Ok(None);
+        }
+        Ok(render_nodes(group.id, &group.rules, assembled.nodes))
+    }
+
+    // 预览链接组的生成结果，列出保留与被过滤的节点，不写回缓存
+    pub async fn preview(&self, group: &LinkGroup) -> Result<GroupPreview, sqlx::Error> {
+        Ok(self.assemble_group(group).await?.preview)
+    }
+
+    // 转换临时的上游地址：只允许拉取公网地址，作为不保存的临时链接走与链接组相同的处理流程
+    pub async fn convert(
+        &self,
+        url: &str,
+        link_type: &str,
+        rules: &GroupRules,
+    ) -> Result<Converted, FetchError> {
+        let fetched = self.fetcher.fetch_public(url, link_type).await?;
+        // 临时链接已带有内容，处理时不会再拉取或写回缓存
+        let link = Link {
+            id: 0,
+            user_id: 0,
+            type_: link_type.to_string(),
+            is_public: false,
+            name: None,
+            slug: None,
+            description: None,
+            content: url.to_string(),
+            cache_content: Some(fetched.body),
+            cache_userinfo: fetched.userinfo,
+            cache_refresh_interval: 0,
+            cache_updated_at: String::new(),
+            created_at: String::new(),
+        };
+        let assembled = self
+            .assemble(0, rules, std::slice::from_ref(&link), &HashMap::new())
+            .await;
+
+        let preview = &assembled.preview;
+        let merged: usize = preview.duplicates.iter().map(|set| set.dropped.len()).sum();
+        let total = preview.kept.len() + preview.dropped.len() + merged;
+        let kept = preview.kept.len();
+        let content = render_nodes(0, rules, assembled.nodes).unwrap_or_default();
+        Ok(Converted {
+            total,
+            kept,
+            content,
+        })
+    }
+
+    // 读取链接组的成员链接及优先级后处理节点
+    async fn assemble_group(&self, group: &LinkGroup) -> Result<Assembled, sqlx::Error> {
+        let links = self.db_client.get_links_by_group(group.id).await?;
+        let priorities: HashMap<i64, i32> = self
+            .db_client
+            .get_group_members(group.id)
+            .await?
+            .into_iter()
+            .map(|member| (member.link_id, member.priority))
+            .collect();
+        Ok(self
+            .assemble(group.id, &group.rules, &links, &priorities)
+            .await)
+    }
+
+    // 读取各 Clash 与分享链接类型的节点，按规则过滤、重命名并去重
+    async fn assemble(
+        &self,
+        group_id: i64,
+        rules: &GroupRules,
+        links: &[Link],
+        priorities: &HashMap<i64, i32>,
+    ) -> Assembled {
+        // 规则在保存时已校验，这里出错只可能是旧数据，忽略对应规则
+        let filter = NodeFilter::new(rules.node_include.as_deref(), rules.node_exclude.as_deref())
+            .unwrap_or_else(|e| {
+                log::warn!("invalid node filter of group {group_id}: {e}");
+                NodeFilter::default()
+            });
+        let renamer = Renamer::new(&rules.rename_rules).unwrap_or_else(|e| {
+            log::warn!("invalid rename rules of group {group_id}: {e}");
+            Renamer::new(&[]).expect("Empty rename rules are valid")
+        });
+        let mut assembled = Assembled::default();
+        let mut candidates = Vec::new();
+        for link in links
+            .iter()
+            .filter(|link| matches!(link.type_.as_str(), "clash" | "uri"))
+        {
+            let Some(content) = self.link_content(link).await else {
+                assembled.fetch_failed = true;
+                continue;
+            };
+            let proxies = match parse_content(&content, &link.type_) {
+                Ok((proxies, diagnostics)) => {
+                    // 分享链接中无法解析的行记入预览
+                    for diagnostic in diagnostics {
+                        assembled.preview.diagnostics.push(LinkDiagnostic {
+                            link_id: link.id,
+                            diagnostic,
+                        });
+                    }
+                    proxies
+                }
+                Err(e) => {
+                    log::warn!("link {} is not a valid Clash config: {e}", link.id);
+                    continue;
+                }
+            };
+
+            let link_name = link_display_name(link);
+            let priority = priorities.get(&link.id).copied().unwrap_or_default();
+            for mut proxy in proxies {
+                let Some(name) = clash::proxy_name(&proxy).map(String::from) else {
+                    continue;
+                };
+                match filter.check(&name) {
+                    FilterVerdict::Kept => {
+                        let renamed = renamer.apply(&name, &link_name);
+                        clash::set_proxy_name(&mut proxy, &renamed);
+                        candidates.push(DedupNode {
+                            link_id: link.id,
+                            link_name: link_name.clone(),
+                            original_name: name,
+                            priority,
+                            proxy,
+                        });
+                    }
+                    verdict => assembled.preview.dropped.push(DroppedNode {
+                        link_id: link.id,
+                        name,
+                        verdict,
+                    }),
+                }
+            }
+            assembled.has_sources = true;
+        }
+
+        let slugs: HashMap<i64, Option<String>> = links
+            .iter()
+            .map(|link| (link.id, link.slug.clone()))
+            .collect();
+        let (nodes, duplicates) = dedup::dedup(candidates, rules.dedup_policy);
+        assembled.preview.duplicates = duplicates;
+        for node in nodes {
+            let name = clash::proxy_name(&node.proxy)
+                .unwrap_or_default()
+                .to_string();
+            for target in Target::CONVERTED {
+                if let Err(reason) = output::check(target, &node.proxy) {
+                    assembled.preview.warnings.push(TargetWarning {
+                        target,
+                        link_id: node.link_id,
+                        name: name.clone(),
+                        reason,
+                    });
+                }
+            }
+            assembled.preview.kept.push(PreviewNode {
+                link_id: node.link_id,
+                name,
+                original_name: node.original_name,
+            });
+            assembled.nodes.push(TemplateNode {
+                link_id: node.link_id,
+                link_slug: slugs.get(&node.link_id).cloned().flatten(),
+                proxy: node.proxy,
+            });
+        }
+        assembled
+    }
+
+    // 重新生成并写回链接组缓存，返回是否有更新
+    pub async fn rebuild(&self, group_id: i64) -> Result<bool, sqlx::Error> {
+        match self.build(group_id).await? {
+            Some(content) => self.db_client.update_group_cache(group_id, &content).await,
+            None => Ok(false),
+        }
+    }
+
+    // 读取链接缓存，从未拉取过的链接先拉取一次
+    async fn link_content(&self, link: &Link) -> Option<String> {
+        if let Some(content) = &link.cache_content {
+            return Some(content.clone());
+        }
+
+        match self.fetcher.fetch(&link.content, &link.type_).await {
+            Ok(fetched) => {
+                let stored = self
+                    .db_client
+                    .update_link_cache(link.id, &fetched.body, fetched.userinfo.as_deref())
+                    .await;
+                if let Err(e) = stored {
+                    log::error!("failed to store cache for link {}: {e}", link.id);
+                }
+                Some(fetched.body)
+            }
+            Err(e) => {
+                log::warn!("failed to fetch link {}: {e}", link.id);
+                None
+            }
+        }
+    }
+}
+
+// 把处理后的节点输出为 Clash 配置，设置了模板时注入模板；模板出错时返回 None
+fn render_nodes(group_id: i64, rules: &GroupRules, nodes: Vec<TemplateNode>) -> Option<String> {
+    if let Some(tpl) = rules.template.as_deref().filter(|t| !t.trim().is_empty()) {
+        // 模板在保存时已校验，这里出错时保留旧的缓存
+        return template::render(tpl, nodes)
+            .inspect_err(|e| log::warn!("failed to render template of group {group_id}: {e}"))
+            .ok();
+    }
+    let proxies = nodes.into_iter().map(|node| node.proxy).collect();
+    Some(clash::merge_config(vec![proxies]))
+}
+
+// 按链接类型把内容解析为 Clash 节点，分享链接中无法解析的行作为诊断一并返回
+fn parse_content(
+    content: &str,
+    link_type: &str,
+) -> Result<(Vec<Mapping>, Vec<Diagnostic>), serde_yaml::Error> {
+    if link_type == "uri" {
+        let parsed = uri::parse_subscription(content);
+        return Ok((
+            parsed.nodes.iter().map(Node::to_clash).collect(),
+            parsed.diagnostics,
+        ));
+    }
+    Ok((clash::parse_proxies(content)?, Vec::new()))
+}
+
+// 链接用于重命名前缀的名称，未命名时依次退回 slug 与 id
+fn link_display_name(link: &Link) -> String {
+    [link.name.as_deref(), link.slug.as_deref()]
+        .into_iter()
+        .flatten()
+        .find(|name| !name.trim().is_empty())
+        .map_or_else(|| format!("#{}", link.id), String::from)
+}
@@ -0,0 +1,198 @@
+use crate::config::RefresherConfig;
+use crate::db::{DbClient, Link};
+use crate::services::fetcher::Fetcher;
+use crate::services::group_builder::GroupBuilder;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Semaphore, watch};
+use tokio::task::{JoinHandle, JoinSet};
+
+// 后台缓存刷新器，按 cache_refresh_interval 定期拉取上游内容并写回缓存
+pub struct CacheRefresher {
+    db_client: DbClient,
+    fetcher: Fetcher,
+    builder: GroupBuilder,
+    limiter: Arc<Semaphore>,
+    scan_interval: Duration,
+}
+
+impl CacheRefresher {
+    pub fn new(db_client: DbClient, fetcher: Fetcher, config: &RefresherConfig) -> Self {
+        Self {
+            builder: GroupBuilder::new(db_client.clone(), fetcher.clone()),
+            db_client,
+            fetcher,
+            limiter: Arc::new(Semaphore::new(config.concurrency)),
+            scan_interval: config.scan_interval(),
+        }
+    }
+
+    // 启动后台任务，shutdown 收到信号后退出并放弃尚未完成的拉取
+    pub fn spawn(self, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.scan_interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = shutdown.changed() => break,
+                }
+
+                tokio::select! {
+                    _ = self.refresh_once() => {}
+                    _ = shutdown.changed() => break,
+                }
+            }
+            log::info!("cache refresher stopped");
+        })
+    }
+
+    // 执行一轮刷新：先拉取过期的链接，再重新生成受影响或已过期的链接组
+    pub async fn refresh_once(&self) {
+        let refreshed = self.refresh_links().await;
+
+        let mut group_ids = BTreeSet::new();
+        for link_id in refreshed {
+            match self.db_client.get_group_ids_by_link(link_id).await {
+                Ok(ids) => group_ids.extend(ids),
+                Err(e) => log::error!("failed to query groups of link {link_id}: {e}"),
+            }
+        }
+        match self.db_client.get_stale_groups().await {
+            Ok(groups) => group_ids.extend(groups.iter().map(|group| group.id)),
+            Err(e) => log::error!("failed to query stale groups: {e}"),
+        }
+
+        for group_id in group_ids {
+            match self.builder.rebuild(group_id).await {
+                Ok(true) => {}
+                // 上游全部拉取失败，保留旧缓存并等到下个刷新间隔再试
+                Ok(false) => {
+                    if let Err(e) = self.db_client.mark_group_refresh_failed(group_id).await {
+                        log::error!("failed to record refresh of group {group_id}: {e}");
+                    }
+                }
+                Err(e) => log::error!("failed to rebuild group {group_id}: {e}"),
+            }
+        }
+    }
+
+    // 并发拉取所有过期的链接，返回成功刷新的链接 id
+    async fn refresh_links(&self) -> Vec<i64> {
+        let links = match self.db_client.get_stale_links().await {
+            Ok(links) => links,
+            Err(e) => {
+                log::error!("failed to query stale links: {e}");
+                return Vec::new();
+            }
+        };
+
+        let mut tasks = JoinSet::new();
+        for link in links {
+            let db_client = self.db_client.clone();
+            let fetcher = self.fetcher.clone();
+            let limiter = self.limiter.clone();
+            tasks.spawn(async move {
+                let _permit = limiter.acquire_owned().await;
+                refresh_link(&db_client, &fetcher, &link)
+                    .await
+                    .then_some(link.id)
+            });
+        }
+
+        let mut refreshed = Vec::new();
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(Some(link_id)) = result {
+                refreshed.push(link_id);
+            }
+        }
+        refreshed
+    }
+}
+
+async fn refresh_link(db_client: &DbClient, fetcher: &Fetcher, link: &Link) -> bool {
+    match fetcher.fetch(&link.content, &link.type_).await {
+        Ok(fetched) => match db_client
+            .update_link_cache(link.id, &fetched.body, fetched.userinfo.as_deref())
+            .await
+        {
+            Ok(updated) => updated,
+            Err(e) => {
+                log::error!("failed to store cache for link {}: {e}", link.id);
+                false
+            }
+        },
+        Err(e) => {
+            log::warn!("failed to fetch link {}: {e}", link.id);
+            // 记录失败时间，避免每轮扫描都重新拉取失效的上游
+            if let Err(e) = db_client.mark_link_refresh_failed(link.id).await {
+                log::error!("failed to record refresh of link {}: {e}", link.id);
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failed_refresh_waits_for_interval() {
+        // 刷新作用于所有过期的链接，使用独立的内存数据库
+        let db = DbClient::connect_in_memory().await.unwrap();
+        let user_id = db.create_user("refresher-user", "pw").await.unwrap();
+        let link_id = db
+            .create_link(
+                user_id,
+                "clash",
+                false,
+                None,
+                None,
+                None,
+                "http://127.0.0.1:1/",
+                None,
+                3600,
+            )
+            .await
+            .unwrap();
+        let group_id = db
+            .create_link_group(
+                user_id,
+                "Team",
+                "refresher-test",
+                None,
+                None,
+                false,
+                None,
+                3600,
+                &Default::default(),
+            )
+            .await
+            .unwrap();
+        assert!(
+            db.add_link_to_group(group_id, link_id, user_id)
+                .await
+                .unwrap()
+        );
+        let stale = || async {
+            let links = db.get_stale_links().await.unwrap();
+            let groups = db.get_stale_groups().await.unwrap();
+            (links.len(), groups.len())
+        };
+        assert_eq!(stale().await, (1, 1));
+
+        // 上游无法连接时不写入缓存，但在刷新间隔内不再重试
+        let fetcher = Fetcher::new(&Default::default());
+        let refresher = CacheRefresher::new(db.clone(), fetcher, &RefresherConfig::default());
+        refresher.refresh_once().await;
+        assert!(
+            db.get_link_by_id(link_id)
+                .await
+                .unwrap()
+                .cache_content
+                .is_none()
+        );
+        assert_eq!(stale().await, (0, 0));
+    }
+}
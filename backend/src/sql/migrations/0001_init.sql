@@ -1,5 +1,3 @@
-PRAGMA foreign_keys = ON;
-
 -- 用户表
 CREATE TABLE IF NOT EXISTS users (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -40,3 +38,27 @@ CREATE TABLE IF NOT EXISTS links (
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
     FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
 );
+
+-- 链接组成员表（链接组包含哪些链接及其顺序）
+CREATE TABLE IF NOT EXISTS group_links (
+    group_id INTEGER NOT NULL,
+    link_id INTEGER NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,  -- 链接在组内的排列顺序，从小到大
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (group_id, link_id),
+    FOREIGN KEY (group_id) REFERENCES link_groups(id) ON DELETE CASCADE,
+    FOREIGN KEY (link_id) REFERENCES links(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_group_links_link_id ON group_links (link_id);
+
+-- 登录会话表（只保存令牌的哈希值）
+CREATE TABLE IF NOT EXISTS sessions (
+    token_hash TEXT PRIMARY KEY,  -- 会话令牌的 SHA-256 十六进制摘要
+    user_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,  -- 创建时间，Unix 秒
+    last_seen_at INTEGER NOT NULL,  -- 最近一次访问时间，Unix 秒
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions (user_id);
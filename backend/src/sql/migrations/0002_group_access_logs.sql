@@ -0,0 +1,13 @@
+-- 链接组订阅拉取记录
+CREATE TABLE group_access_logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    group_id INTEGER NOT NULL,
+    accessed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    client_ip TEXT NOT NULL,
+    user_agent TEXT,
+    key_used TEXT,  -- 请求携带的访问密钥，用于排查密钥泄露
+    status INTEGER NOT NULL,  -- 返回的 HTTP 状态码
+    FOREIGN KEY (group_id) REFERENCES link_groups(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_group_access_logs_group_id ON group_access_logs (group_id, id);
@@ -0,0 +1,2 @@
+-- 上游返回的 Subscription-Userinfo 流量信息，与缓存内容一同更新
+ALTER TABLE links ADD COLUMN cache_userinfo TEXT;
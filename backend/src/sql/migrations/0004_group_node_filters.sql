@@ -0,0 +1,3 @@
+-- 链接组的节点过滤规则，每行一个正则表达式，按节点名称匹配
+ALTER TABLE link_groups ADD COLUMN node_include TEXT;
+ALTER TABLE link_groups ADD COLUMN node_exclude TEXT;
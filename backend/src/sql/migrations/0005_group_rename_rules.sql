@@ -0,0 +1,2 @@
+-- 链接组的节点重命名规则，JSON 数组，按顺序执行
+ALTER TABLE link_groups ADD COLUMN rename_rules TEXT NOT NULL DEFAULT '[]';
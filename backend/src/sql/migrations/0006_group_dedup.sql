@@ -0,0 +1,4 @@
+-- 链接在组内的优先级，去重策略为 keep_priority 时数值大的链接优先保留
+ALTER TABLE group_links ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+-- 链接组的重复节点处理策略
+ALTER TABLE link_groups ADD COLUMN dedup_policy TEXT NOT NULL DEFAULT 'off';
@@ -0,0 +1,2 @@
+-- 链接组的 Clash 配置模板，为空时使用内置的默认配置
+ALTER TABLE link_groups ADD COLUMN template TEXT;
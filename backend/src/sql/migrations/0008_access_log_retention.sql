@@ -0,0 +1,11 @@
+-- 拉取记录按时间清理时使用的索引
+CREATE INDEX idx_group_access_logs_accessed_at ON group_access_logs (accessed_at);
+
+-- 访问密钥不再明文保存，已有记录一并脱敏；User-Agent 截断到 256 个字符
+UPDATE group_access_logs
+SET key_used = CASE
+        WHEN key_used IS NULL OR key_used = '' THEN NULL
+        WHEN length(key_used) >= 8 THEN substr(key_used, 1, 2) || '****' || substr(key_used, -2)
+        ELSE '****'
+    END,
+    user_agent = substr(user_agent, 1, 256);
@@ -0,0 +1,3 @@
+-- 最近一次刷新失败的时间，失败后同样等待一个刷新间隔再重试
+ALTER TABLE links ADD COLUMN cache_attempted_at DATETIME;
+ALTER TABLE link_groups ADD COLUMN cache_attempted_at DATETIME;
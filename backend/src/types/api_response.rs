@@ -1,19 +1,21 @@
 use axum::{
     Json,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
-    http::StatusCode,
 };
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 
 // 业务状态码与消息的绑定
 #[derive(Debug, Clone, Copy)]
 pub enum BizCode {
-    Success,      // 成功
-    BadRequest,   // 请求错误
-    Unauthorized, // 未授权
-    Forbidden,    // 禁止访问
-    NotFound,     // 资源不存在
-    ServerError,  // 服务器错误
+    Success,         // 成功
+    BadRequest,      // 请求错误
+    Unauthorized,    // 未授权
+    Forbidden,       // 禁止访问
+    NotFound,        // 资源不存在
+    Conflict,        // 资源冲突
+    TooManyRequests, // 请求过于频繁
+    ServerError,     // 服务器错误
 }
 
 impl BizCode {
@@ -25,6 +27,8 @@ impl BizCode {
             BizCode::Unauthorized => 401,
             BizCode::Forbidden => 403,
             BizCode::NotFound => 404,
+            BizCode::Conflict => 409,
+            BizCode::TooManyRequests => 429,
             BizCode::ServerError => 500,
         }
     }
@@ -37,6 +41,8 @@ impl BizCode {
             BizCode::Unauthorized => "未授权访问",
             BizCode::Forbidden => "禁止访问",
             BizCode::NotFound => "资源不存在",
+            BizCode::Conflict => "资源冲突",
+            BizCode::TooManyRequests => "请求过于频繁，请稍后再试",
             BizCode::ServerError => "服务器内部错误",
         }
     }
@@ -49,6 +55,8 @@ impl BizCode {
             BizCode::Unauthorized => StatusCode::UNAUTHORIZED,
             BizCode::Forbidden => StatusCode::FORBIDDEN,
             BizCode::NotFound => StatusCode::NOT_FOUND,
+            BizCode::Conflict => StatusCode::CONFLICT,
+            BizCode::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
             BizCode::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -64,6 +72,8 @@ pub struct ApiResponse<T> {
     // 内部字段，不序列化到 JSON
     #[serde(skip)]
     status_code: StatusCode,
+    #[serde(skip)]
+    headers: HeaderMap, // 额外的响应头
 }
 
 impl<T> ApiResponse<T> {
@@ -76,6 +86,7 @@ impl<T> ApiResponse<T> {
             msg: message,
             data,
             status_code: code.status_code(),
+            headers: HeaderMap::new(),
         }
     }
 
@@ -90,6 +101,7 @@ impl<T> ApiResponse<T> {
     }
 
     // 成功响应，自定义消息
+    #[allow(dead_code)]
     pub fn success_with_msg(data: T, msg: &str) -> Self {
         Self::new(BizCode::Success, Some(msg), Some(data))
     }
@@ -100,21 +112,36 @@ impl<T> ApiResponse<T> {
     }
 
     // 设置消息
+    #[allow(dead_code)]
     pub fn with_msg(mut self, msg: &str) -> Self {
         self.msg = msg.to_string();
         self
     }
 
     // 设置数据
+    #[allow(dead_code)]
     pub fn with_data(mut self, data: T) -> Self {
         self.data = Some(data);
         self
     }
+
+    // 添加响应头
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    // 设置 Retry-After 响应头（秒），用于 429 响应
+    pub fn with_retry_after(self, secs: u64) -> Self {
+        self.with_header(header::RETRY_AFTER, HeaderValue::from(secs))
+    }
 }
 
 // 为 ApiResponse 实现 IntoResponse，使其可以直接作为 Axum 的响应返回
 impl<T: Serialize> IntoResponse for ApiResponse<T> {
     fn into_response(self) -> Response {
-        (self.status_code, Json(self)).into_response()
+        let mut response = (self.status_code, Json(&self)).into_response();
+        response.headers_mut().extend(self.headers);
+        response
     }
 }
\ No newline at end of file
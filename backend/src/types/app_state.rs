@@ -1,8 +1,39 @@
+use crate::config::Config;
 use crate::db::DbClient;
+#[cfg(test)]
+use crate::services::fetcher::Fetcher;
+use crate::services::group_builder::GroupBuilder;
+use crate::types::login_limiter::LoginLimiter;
 use crate::types::session_store::SessionStore;
+#[cfg(test)]
+use std::sync::Arc;
 // 应用状态
 #[derive(Clone)]
 pub struct AppState {
-    pub db_client: DbClient, // 数据库
-    pub sessions: SessionStore, // 内存 Session 存储
+    pub db_client: DbClient,         // 数据库
+    pub sessions: SessionStore,      // Session 存储
+    pub login_limiter: LoginLimiter, // 登录限流
+    pub group_builder: GroupBuilder, // 链接组配置生成
+    pub config: Config,              // 应用配置
+}
+
+#[cfg(test)]
+impl AppState {
+    // 测试用的应用状态：内存会话、默认限流规则与默认配置
+    pub fn for_test(db_client: DbClient) -> Arc<Self> {
+        Self::for_test_with_fetcher(db_client, Fetcher::new(&Default::default()))
+    }
+
+    // 使用指定的拉取器，便于替换上游
+    pub fn for_test_with_fetcher(db_client: DbClient, fetcher: Fetcher) -> Arc<Self> {
+        use crate::types::session_store::MemorySessionStore;
+
+        Arc::new(Self {
+            group_builder: GroupBuilder::new(db_client.clone(), fetcher),
+            db_client,
+            sessions: SessionStore::Memory(MemorySessionStore::new()),
+            login_limiter: LoginLimiter::new(Default::default()),
+            config: Config::default(),
+        })
+    }
 }
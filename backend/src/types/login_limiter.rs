@@ -0,0 +1,413 @@
+use crate::config::LoginLimitConfig;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+// 登录限制的统计维度
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+enum LimitKey {
+    Ip(IpAddr),
+    User(String),
+}
+
+// 某个维度上的连续失败记录及尚未出结果的尝试数
+#[derive(Clone, Debug)]
+struct Attempts {
+    failures: u32,
+    in_flight: u32,
+    last_failure: Instant,
+    blocked_until: Option<Instant>,
+}
+
+impl Attempts {
+    fn new(now: Instant) -> Self {
+        Self {
+            failures: 0,
+            in_flight: 0,
+            last_failure: now,
+            blocked_until: None,
+        }
+    }
+}
+
+// 一次登录尝试的结果
+enum Outcome {
+    Success,
+    Failure,
+    Abandoned, // 请求中途取消，仅释放占位
+}
+
+// 登录被拒绝的原因
+#[derive(Debug, PartialEq, Eq)]
+pub enum LoginBlocked {
+    Backoff(Duration), // 失败过多，需要等待后重试
+    Locked(Duration),  // 账户被临时锁定
+}
+
+impl LoginBlocked {
+    // 距离可以重试的秒数，向上取整
+    pub fn retry_after_secs(&self) -> u64 {
+        let wait = match self {
+            LoginBlocked::Backoff(wait) | LoginBlocked::Locked(wait) => *wait,
+        };
+        wait.as_secs() + u64::from(wait.subsec_nanos() > 0)
+    }
+}
+
+// 登录限流器，按 IP 和用户名分别统计连续失败次数
+// 超过免费次数后按指数退避，用户名连续失败达到阈值后临时锁定
+#[derive(Clone)]
+pub struct LoginLimiter {
+    attempts: Arc<Mutex<HashMap<LimitKey, Attempts>>>,
+    config: LoginLimitConfig,
+}
+
+impl LoginLimiter {
+    pub fn new(config: LoginLimitConfig) -> Self {
+        Self {
+            attempts: Arc::new(Mutex::new(HashMap::new())),
+            config,
+        }
+    }
+
+    // 检查是否允许本次登录尝试，允许时在同一把锁下占位，返回的凭据须在校验后结算
+    // 进行中的尝试按失败预估，避免并发请求同时通过检查
+    pub fn check(&self, ip: IpAddr, username: &str) -> Result<LoginAttempt, LoginBlocked> {
+        let now = Instant::now();
+        let mut attempts = self.attempts.lock().expect("Failed to lock login limiter");
+
+        let user_key = LimitKey::User(username.to_string());
+        if let Some(user) = attempts.get(&user_key) {
+            if let Some(wait) = remaining(user, now) {
+                return Err(if user.failures >= self.config.lockout_threshold {
+                    LoginBlocked::Locked(wait)
+                } else {
+                    LoginBlocked::Backoff(wait)
+                });
+            }
+            if let Some(blocked) = self.pending_block(user, now, true) {
+                return Err(blocked);
+            }
+        }
+        let ip_key = LimitKey::Ip(ip);
+        if let Some(by_ip) = attempts.get(&ip_key) {
+            if let Some(wait) = remaining(by_ip, now) {
+                return Err(LoginBlocked::Backoff(wait));
+            }
+            if let Some(blocked) = self.pending_block(by_ip, now, false) {
+                return Err(blocked);
+            }
+        }
+
+        for key in [ip_key, user_key] {
+            attempts
+                .entry(key)
+                .or_insert_with(|| Attempts::new(now))
+                .in_flight += 1;
+        }
+        Ok(LoginAttempt {
+            limiter: self.clone(),
+            ip,
+            username: username.to_string(),
+            settled: false,
+        })
+    }
+
+    // 假设进行中的尝试全部失败时，新的尝试是否会被拒绝
+    fn pending_block(
+        &self,
+        attempts: &Attempts,
+        now: Instant,
+        is_user: bool,
+    ) -> Option<LoginBlocked> {
+        if attempts.in_flight == 0 {
+            return None;
+        }
+        let failures = if now.duration_since(attempts.last_failure) >= self.config.reset_after() {
+            0
+        } else {
+            attempts.failures
+        };
+        let pending = failures + attempts.in_flight;
+        if is_user && pending >= self.config.lockout_threshold {
+            return Some(LoginBlocked::Locked(self.config.lockout()));
+        }
+        self.backoff(pending).map(LoginBlocked::Backoff)
+    }
+
+    // 释放一次尝试的占位并记录其结果
+    fn settle(&self, ip: IpAddr, username: &str, outcome: Outcome) {
+        let now = Instant::now();
+        let mut attempts = self.attempts.lock().expect("Failed to lock login limiter");
+        let user_key = LimitKey::User(username.to_string());
+        for key in [LimitKey::Ip(ip), user_key.clone()] {
+            if let Some(entry) = attempts.get_mut(&key) {
+                entry.in_flight = entry.in_flight.saturating_sub(1);
+            }
+        }
+
+        match outcome {
+            Outcome::Success => {
+                // 登录成功后清除该用户名的失败记录；IP 记录随时间自然过期
+                if let Some(user) = attempts.get_mut(&user_key) {
+                    user.failures = 0;
+                    user.blocked_until = None;
+                    if user.in_flight == 0 {
+                        attempts.remove(&user_key);
+                    }
+                }
+            }
+            Outcome::Failure => {
+                for key in [LimitKey::Ip(ip), user_key] {
+                    let is_user = matches!(key, LimitKey::User(_));
+                    let entry = attempts.entry(key).or_insert_with(|| Attempts::new(now));
+                    // 距上次失败足够久则重新计数
+                    if now.duration_since(entry.last_failure) >= self.config.reset_after() {
+                        entry.failures = 0;
+                    }
+                    entry.failures += 1;
+                    entry.last_failure = now;
+
+                    let delay = if is_user && entry.failures >= self.config.lockout_threshold {
+                        Some(self.config.lockout())
+                    } else {
+                        self.backoff(entry.failures)
+                    };
+                    entry.blocked_until = delay.map(|delay| now + delay);
+                }
+            }
+            Outcome::Abandoned => {}
+        }
+    }
+
+    // 清理已过期的记录，返回清理的数量
+    pub fn purge_expired(&self) -> usize {
+        let now = Instant::now();
+        let reset_after = self.config.reset_after();
+        let mut attempts = self.attempts.lock().expect("Failed to lock login limiter");
+        let before = attempts.len();
+        attempts.retain(|_, a| {
+            a.in_flight > 0
+                || remaining(a, now).is_some()
+                || (a.failures > 0 && now.duration_since(a.last_failure) < reset_after)
+        });
+        before - attempts.len()
+    }
+
+    // 启动定期清理过期记录的后台任务，shutdown 收到信号后退出
+    pub fn spawn_sweeper(
+        &self,
+        period: Duration,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> JoinHandle<()> {
+        let limiter = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        limiter.purge_expired();
+                    }
+                    _ = shutdown.changed() => break,
+                }
+            }
+        })
+    }
+
+    // 第 failures 次失败后的等待时长，免费次数内不等待
+    fn backoff(&self, failures: u32) -> Option<Duration> {
+        let exponent = failures.checked_sub(self.config.free_attempts)?;
+        let delay = self
+            .config
+            .base_delay()
+            .checked_mul(2u32.saturating_pow(exponent))
+            .unwrap_or(Duration::MAX);
+        Some(delay.min(self.config.max_delay()))
+    }
+}
+
+// 已通过检查、尚未结算的登录尝试，丢弃时仅释放占位
+pub struct LoginAttempt {
+    limiter: LoginLimiter,
+    ip: IpAddr,
+    username: String,
+    settled: bool,
+}
+
+impl LoginAttempt {
+    // 密码校验通过
+    pub fn succeed(mut self) {
+        self.settled = true;
+        self.limiter
+            .settle(self.ip, &self.username, Outcome::Success);
+    }
+
+    // 密码校验失败，占位转为一次失败记录
+    pub fn fail(mut self) {
+        self.settled = true;
+        self.limiter
+            .settle(self.ip, &self.username, Outcome::Failure);
+    }
+}
+
+impl Drop for LoginAttempt {
+    fn drop(&mut self) {
+        if !self.settled {
+            self.limiter
+                .settle(self.ip, &self.username, Outcome::Abandoned);
+        }
+    }
+}
+
+fn remaining(attempts: &Attempts, now: Instant) -> Option<Duration> {
+    attempts
+        .blocked_until
+        .filter(|until| *until > now)
+        .map(|until| until - now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> LoginLimiter {
+        LoginLimiter::new(LoginLimitConfig {
+            free_attempts: 2,
+            base_delay_secs: 1,
+            max_delay_secs: 8,
+            lockout_threshold: 6,
+            lockout_secs: 600,
+            reset_after_secs: 3600,
+        })
+    }
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last])
+    }
+
+    // 直接记录一次失败，不经过检查
+    fn record_failure(limiter: &LoginLimiter, ip: IpAddr, username: &str) {
+        limiter.settle(ip, username, Outcome::Failure);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_exponential_backoff() {
+        let limiter = limiter();
+
+        // 免费次数内不受限制
+        record_failure(&limiter, ip(1), "alice");
+        assert!(limiter.check(ip(1), "alice").is_ok());
+
+        record_failure(&limiter, ip(1), "alice");
+        assert_eq!(
+            limiter.check(ip(1), "alice").err(),
+            Some(LoginBlocked::Backoff(Duration::from_secs(1)))
+        );
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(limiter.check(ip(1), "alice").is_ok());
+
+        record_failure(&limiter, ip(1), "alice");
+        assert_eq!(
+            limiter
+                .check(ip(1), "alice")
+                .err()
+                .unwrap()
+                .retry_after_secs(),
+            2
+        );
+
+        // 等待时长有上限
+        for _ in 0..2 {
+            record_failure(&limiter, ip(1), "alice");
+        }
+        assert_eq!(
+            limiter
+                .check(ip(1), "alice")
+                .err()
+                .unwrap()
+                .retry_after_secs(),
+            8
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ip_and_user_are_tracked_separately() {
+        let limiter = limiter();
+        for i in 0..3 {
+            record_failure(&limiter, ip(1), &format!("user{i}"));
+        }
+        // 同一 IP 换用户名依然受限，其他 IP 登录该用户名不受影响
+        assert!(matches!(
+            limiter.check(ip(1), "bob"),
+            Err(LoginBlocked::Backoff(_))
+        ));
+        assert!(limiter.check(ip(2), "user0").is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lockout_and_success_reset() {
+        let limiter = limiter();
+        for i in 0..6 {
+            record_failure(&limiter, ip(i), "alice");
+        }
+        let blocked = limiter.check(ip(100), "alice").err().unwrap();
+        assert_eq!(blocked, LoginBlocked::Locked(Duration::from_secs(600)));
+
+        tokio::time::advance(Duration::from_secs(600)).await;
+        limiter.check(ip(100), "alice").unwrap().succeed();
+        record_failure(&limiter, ip(100), "alice");
+        assert!(limiter.check(ip(101), "alice").is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_purge_expired() {
+        let limiter = limiter();
+        record_failure(&limiter, ip(1), "alice");
+        assert_eq!(limiter.purge_expired(), 0);
+        tokio::time::advance(Duration::from_secs(3600)).await;
+        assert_eq!(limiter.purge_expired(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrent_attempts_are_reserved() {
+        let limiter = limiter();
+
+        // 同时发起的尝试按失败预估，超出免费次数的请求在校验前即被拒绝
+        let mut tasks = tokio::task::JoinSet::new();
+        for i in 0..10 {
+            let limiter = limiter.clone();
+            tasks.spawn(async move {
+                let attempt = limiter.check(ip(i), "alice").ok()?;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                attempt.fail();
+                Some(())
+            });
+        }
+        let passed = tasks.join_all().await.into_iter().flatten().count();
+        assert_eq!(passed, 2);
+        assert_eq!(
+            limiter.check(ip(100), "alice").err(),
+            Some(LoginBlocked::Backoff(Duration::from_secs(1)))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_abandoned_attempt_releases_reservation() {
+        let limiter = limiter();
+        let first = limiter.check(ip(1), "alice").unwrap();
+        let second = limiter.check(ip(1), "alice").unwrap();
+        assert!(limiter.check(ip(1), "alice").is_err());
+
+        // 成功或中途取消都只释放占位，不计入失败
+        first.succeed();
+        drop(second);
+        let retries = [limiter.check(ip(1), "alice"), limiter.check(ip(1), "alice")];
+        assert!(retries.iter().all(Result::is_ok));
+        drop(retries);
+        assert_eq!(limiter.purge_expired(), 2);
+    }
+}
@@ -1,3 +1,4 @@
-pub(crate) mod session_store;
+pub(crate) mod api_response;
 pub(crate) mod app_state;
-pub(crate) mod api_response;
\ No newline at end of file
+pub(crate) mod login_limiter;
+pub(crate) mod session_store;
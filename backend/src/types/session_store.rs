@@ -1,75 +1,478 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use crate::config::{SessionBackend, SessionConfig};
+use crate::db::DbClient;
 use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 
-// 会话存储结构体，封装会话管理功能
-#[derive(Clone, Default)]
-pub struct SessionStore {
-    // 使用Arc和Mutex实现线程安全的HashMap存储
-    sessions: Arc<Mutex<HashMap<String, String>>>,
+// 会话自创建起的最长有效期
+const DEFAULT_ABSOLUTE_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+// 会话闲置超过该时长即失效，每次访问都会顺延
+const DEFAULT_IDLE_TTL: Duration = Duration::from_secs(24 * 3600);
+
+// 会话存储，可选内存实现（用于测试）或 SQLite 持久化实现
+#[derive(Clone)]
+pub enum SessionStore {
+    Memory(MemorySessionStore),
+    Sqlite(SqliteSessionStore),
 }
 
 impl SessionStore {
-    // 创建新的会话存储实例
-    pub fn new() -> Self {
-        Self {
-            sessions: Arc::new(Mutex::new(HashMap::new())),
+    // 按配置创建会话存储
+    pub fn from_config(config: &SessionConfig, db_client: DbClient) -> Self {
+        match config.store {
+            SessionBackend::Memory => SessionStore::Memory(MemorySessionStore::with_ttl(
+                config.absolute_ttl(),
+                config.idle_ttl(),
+            )),
+            SessionBackend::Sqlite => SessionStore::Sqlite(SqliteSessionStore::with_ttl(
+                db_client,
+                config.absolute_ttl(),
+                config.idle_ttl(),
+            )),
         }
     }
 
-    // 生成随机的会话令牌
-    fn generate_token(&self) -> String {
-        let mut rng = rand::rng();
-        let token: String = (0..32)
-            .map(|_| {
-                let c = rng.random_range(0..62);
-                match c {
-                    0..=9 => (b'0' + c as u8) as char,
-                    10..=35 => (b'a' + (c - 10) as u8) as char,
-                    36..=61 => (b'A' + (c - 36) as u8) as char,
-                    _ => unreachable!(),
+    // 添加新会话并返回令牌
+    pub async fn add_session(&self, user_name: &str) -> Result<String, sqlx::Error> {
+        match self {
+            SessionStore::Memory(store) => Ok(store.add_session(user_name)),
+            SessionStore::Sqlite(store) => store.add_session(user_name).await,
+        }
+    }
+
+    // 根据令牌获取用户名，会话过期或不存在时返回 None
+    pub async fn get_user(&self, token: &str) -> Option<String> {
+        match self {
+            SessionStore::Memory(store) => store.get_user(token),
+            SessionStore::Sqlite(store) => store.get_user(token).await,
+        }
+    }
+
+    // 移除会话，返回会话是否存在
+    pub async fn remove_session(&self, token: &str) -> bool {
+        match self {
+            SessionStore::Memory(store) => store.remove_session(token).is_some(),
+            SessionStore::Sqlite(store) => store.remove_session(token).await,
+        }
+    }
+
+    // 移除某个用户的全部会话，返回移除的数量
+    pub async fn remove_user_sessions(&self, user_name: &str) -> u64 {
+        match self {
+            SessionStore::Memory(store) => store.remove_user_sessions(user_name) as u64,
+            SessionStore::Sqlite(store) => store.remove_user_sessions(user_name).await,
+        }
+    }
+
+    // 清理所有过期会话，返回清理的数量
+    pub async fn purge_expired(&self) -> u64 {
+        match self {
+            SessionStore::Memory(store) => store.purge_expired() as u64,
+            SessionStore::Sqlite(store) => store.purge_expired().await,
+        }
+    }
+
+    // 启动定期清理过期会话的后台任务，shutdown 收到信号后退出
+    pub fn spawn_sweeper(
+        &self,
+        period: Duration,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        let purged = store.purge_expired().await;
+                        if purged > 0 {
+                            log::debug!("purged {purged} expired sessions");
+                        }
+                    }
+                    _ = shutdown.changed() => break,
                 }
-            })
-            .collect();
-        token
+            }
+        })
+    }
+}
+
+// 生成随机的会话令牌
+fn generate_token() -> String {
+    let mut rng = rand::rng();
+    let token: String = (0..32)
+        .map(|_| {
+            let c = rng.random_range(0..62);
+            match c {
+                0..=9 => (b'0' + c as u8) as char,
+                10..=35 => (b'a' + (c - 10) as u8) as char,
+                36..=61 => (b'A' + (c - 36) as u8) as char,
+                _ => unreachable!(),
+            }
+        })
+        .collect();
+    token
+}
+
+// 单个会话的信息
+#[derive(Clone, Debug)]
+struct Session {
+    user_name: String,
+    created_at: Instant, // 创建时间
+    last_seen: Instant,  // 最近一次访问时间
+}
+
+impl Session {
+    fn is_expired(&self, now: Instant, absolute_ttl: Duration, idle_ttl: Duration) -> bool {
+        now.duration_since(self.created_at) >= absolute_ttl
+            || now.duration_since(self.last_seen) >= idle_ttl
+    }
+}
+
+// 内存会话存储，进程重启后会话全部失效
+#[derive(Clone)]
+pub struct MemorySessionStore {
+    // 使用Arc和Mutex实现线程安全的HashMap存储
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    absolute_ttl: Duration,
+    idle_ttl: Duration,
+}
+
+impl Default for MemorySessionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemorySessionStore {
+    // 创建新的会话存储实例，使用默认有效期
+    pub fn new() -> Self {
+        Self::with_ttl(DEFAULT_ABSOLUTE_TTL, DEFAULT_IDLE_TTL)
+    }
+
+    // 创建指定绝对有效期与闲置有效期的会话存储实例
+    pub fn with_ttl(absolute_ttl: Duration, idle_ttl: Duration) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            absolute_ttl,
+            idle_ttl,
+        }
     }
 
     // 添加新会话并返回令牌
     pub fn add_session(&self, user_name: &str) -> String {
-        let token = self.generate_token();
+        let token = generate_token();
+        let now = Instant::now();
 
         // 锁定HashMap并插入新会话
         let mut sessions = self.sessions.lock().expect("Failed to lock session store");
-        sessions.insert(token.clone(), user_name.to_string());
+        sessions.insert(
+            token.clone(),
+            Session {
+                user_name: user_name.to_string(),
+                created_at: now,
+                last_seen: now,
+            },
+        );
 
         token
     }
 
-    // 根据令牌获取用户名
+    // 根据令牌获取用户名，过期的会话会被移除，有效的会话顺延闲置期
     pub fn get_user(&self, token: &str) -> Option<String> {
-        let sessions = self.sessions.lock().expect("Failed to lock session store");
-        sessions.get(token).cloned()
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().expect("Failed to lock session store");
+        let session = sessions.get_mut(token)?;
+        if session.is_expired(now, self.absolute_ttl, self.idle_ttl) {
+            sessions.remove(token);
+            return None;
+        }
+        session.last_seen = now;
+        Some(session.user_name.clone())
     }
 
     // 移除会话
     pub fn remove_session(&self, token: &str) -> Option<String> {
         let mut sessions = self.sessions.lock().expect("Failed to lock session store");
-        sessions.remove(token)
+        sessions.remove(token).map(|session| session.user_name)
     }
 
-    // 检查会话是否存在
+    // 移除某个用户的全部会话
+    pub fn remove_user_sessions(&self, user_name: &str) -> usize {
+        let mut sessions = self.sessions.lock().expect("Failed to lock session store");
+        let before = sessions.len();
+        sessions.retain(|_, session| session.user_name != user_name);
+        before - sessions.len()
+    }
+
+    // 检查会话是否存在且未过期
+    #[cfg(test)]
     pub fn has_session(&self, token: &str) -> bool {
+        let now = Instant::now();
         let sessions = self.sessions.lock().expect("Failed to lock session store");
-        sessions.contains_key(token)
+        sessions
+            .get(token)
+            .is_some_and(|session| !session.is_expired(now, self.absolute_ttl, self.idle_ttl))
+    }
+
+    // 清理所有过期会话，返回清理的数量
+    pub fn purge_expired(&self) -> usize {
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().expect("Failed to lock session store");
+        let before = sessions.len();
+        sessions.retain(|_, session| !session.is_expired(now, self.absolute_ttl, self.idle_ttl));
+        before - sessions.len()
+    }
+}
+
+// SQLite 会话存储，进程重启后会话依然有效，数据库中只保存令牌的哈希值
+#[derive(Clone)]
+pub struct SqliteSessionStore {
+    db_client: DbClient,
+    absolute_ttl: Duration,
+    idle_ttl: Duration,
+}
+
+impl SqliteSessionStore {
+    // 创建指定绝对有效期与闲置有效期的持久化会话存储
+    pub fn with_ttl(db_client: DbClient, absolute_ttl: Duration, idle_ttl: Duration) -> Self {
+        Self {
+            db_client,
+            absolute_ttl,
+            idle_ttl,
+        }
     }
+
+    pub async fn add_session(&self, user_name: &str) -> Result<String, sqlx::Error> {
+        let user = self.db_client.get_user_by_username(user_name).await?;
+        let token = generate_token();
+        self.db_client
+            .create_session(&hash_token(&token), user.id, unix_now())
+            .await?;
+        Ok(token)
+    }
+
+    pub async fn get_user(&self, token: &str) -> Option<String> {
+        let token_hash = hash_token(token);
+        let session = match self.db_client.get_session(&token_hash).await {
+            Ok(session) => session?,
+            Err(e) => {
+                log::error!("failed to load session: {e}");
+                return None;
+            }
+        };
+
+        let now = unix_now();
+        let absolute_ttl = self.absolute_ttl.as_secs() as i64;
+        let idle_ttl = self.idle_ttl.as_secs() as i64;
+        if now - session.created_at >= absolute_ttl || now - session.last_seen_at >= idle_ttl {
+            let _ = self.db_client.delete_session(&token_hash).await;
+            return None;
+        }
+
+        if let Err(e) = self.db_client.touch_session(&token_hash, now).await {
+            log::warn!("failed to renew session: {e}");
+        }
+        Some(session.username)
+    }
+
+    pub async fn remove_session(&self, token: &str) -> bool {
+        self.db_client
+            .delete_session(&hash_token(token))
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("failed to remove session: {e}");
+                false
+            })
+    }
+
+    pub async fn remove_user_sessions(&self, user_name: &str) -> u64 {
+        self.db_client
+            .delete_sessions_by_username(user_name)
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("failed to remove sessions of {user_name}: {e}");
+                0
+            })
+    }
+
+    pub async fn purge_expired(&self) -> u64 {
+        let now = unix_now();
+        let created_before = now - self.absolute_ttl.as_secs() as i64;
+        let seen_before = now - self.idle_ttl.as_secs() as i64;
+        self.db_client
+            .delete_expired_sessions(created_before, seen_before)
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("failed to purge expired sessions: {e}");
+                0
+            })
+    }
+}
+
+// 计算令牌的 SHA-256 摘要，令牌本身为高熵随机串，无需加盐
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock is before Unix epoch")
+        .as_secs() as i64
 }
 
 // 实现Display trait以便于打印
-impl fmt::Display for SessionStore {
+impl fmt::Display for MemorySessionStore {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let sessions = self.sessions.lock().expect("Failed to lock session store");
-        write!(f, "SessionStore with {} sessions", sessions.len())
+        write!(f, "MemorySessionStore with {} sessions", sessions.len())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_ttl_slides() {
+        let store = MemorySessionStore::with_ttl(Duration::from_secs(100), Duration::from_secs(10));
+        let token = store.add_session("alice");
+
+        // 闲置期内访问会顺延闲置期
+        tokio::time::advance(Duration::from_secs(8)).await;
+        assert_eq!(store.get_user(&token).as_deref(), Some("alice"));
+        tokio::time::advance(Duration::from_secs(8)).await;
+        assert_eq!(store.get_user(&token).as_deref(), Some("alice"));
+
+        // 闲置超时后失效
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(store.get_user(&token), None);
+        assert!(!store.has_session(&token));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_absolute_ttl() {
+        let store = MemorySessionStore::with_ttl(Duration::from_secs(20), Duration::from_secs(10));
+        let token = store.add_session("alice");
+
+        // 持续访问也无法超过绝对有效期
+        for _ in 0..3 {
+            tokio::time::advance(Duration::from_secs(6)).await;
+            assert!(store.get_user(&token).is_some());
+        }
+        tokio::time::advance(Duration::from_secs(6)).await;
+        assert_eq!(store.get_user(&token), None);
+    }
+
+    #[tokio::test]
+    async fn test_remove_user_sessions() {
+        let store = SessionStore::Memory(MemorySessionStore::new());
+        let first = store.add_session("alice").await.unwrap();
+        let second = store.add_session("alice").await.unwrap();
+        let other = store.add_session("bob").await.unwrap();
+
+        assert!(store.remove_session(&first).await);
+        assert!(!store.remove_session(&first).await);
+        assert_eq!(store.remove_user_sessions("alice").await, 1);
+        assert_eq!(store.get_user(&second).await, None);
+        assert_eq!(store.get_user(&other).await.as_deref(), Some("bob"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_purge_expired() {
+        let store = MemorySessionStore::with_ttl(Duration::from_secs(100), Duration::from_secs(10));
+        store.add_session("alice");
+        tokio::time::advance(Duration::from_secs(5)).await;
+        let token = store.add_session("bob");
+
+        tokio::time::advance(Duration::from_secs(6)).await;
+        assert_eq!(store.purge_expired(), 1);
+        assert!(store.has_session(&token));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store() {
+        let db = DbClient::connect(&DatabaseConfig::default()).await.unwrap();
+        let user_id = db.create_user("session-user", "hash123").await.unwrap();
+        let store = SessionStore::Sqlite(SqliteSessionStore::with_ttl(
+            db.clone(),
+            DEFAULT_ABSOLUTE_TTL,
+            DEFAULT_IDLE_TTL,
+        ));
+
+        let token = store.add_session("session-user").await.unwrap();
+        assert_eq!(
+            store.get_user(&token).await.as_deref(),
+            Some("session-user")
+        );
+
+        // 数据库中只保存令牌的哈希值
+        assert!(db.get_session(&token).await.unwrap().is_none());
+        assert!(db.get_session(&hash_token(&token)).await.unwrap().is_some());
+
+        // 新的存储实例（模拟重启）仍能识别会话
+        let restarted = SessionStore::Sqlite(SqliteSessionStore::with_ttl(
+            db.clone(),
+            DEFAULT_ABSOLUTE_TTL,
+            DEFAULT_IDLE_TTL,
+        ));
+        assert_eq!(
+            restarted.get_user(&token).await.as_deref(),
+            Some("session-user")
+        );
+
+        assert!(restarted.remove_session(&token).await);
+        assert_eq!(store.get_user(&token).await, None);
+
+        // 注销该用户的全部会话
+        let first = store.add_session("session-user").await.unwrap();
+        let second = store.add_session("session-user").await.unwrap();
+        assert_eq!(store.remove_user_sessions("session-user").await, 2);
+        assert_eq!(store.get_user(&first).await, None);
+        assert_eq!(store.get_user(&second).await, None);
+
+        db.delete_user(user_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store_expiry() {
+        // 清理过期会话会影响整张表，使用独立的内存数据库
+        let db = DbClient::connect_in_memory().await.unwrap();
+        let user_id = db.create_user("session-user2", "hash123").await.unwrap();
+        let store = SqliteSessionStore::with_ttl(
+            db.clone(),
+            Duration::from_secs(3600),
+            Duration::from_secs(60),
+        );
+
+        // 直接写入一个闲置超时的会话
+        let now = unix_now();
+        db.create_session(&hash_token("stale-token"), user_id, now - 120)
+            .await
+            .unwrap();
+        assert_eq!(store.get_user("stale-token").await, None);
+        assert!(
+            db.get_session(&hash_token("stale-token"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        db.create_session(&hash_token("old-token"), user_id, now - 7200)
+            .await
+            .unwrap();
+        assert_eq!(store.purge_expired().await, 1);
+
+        db.delete_user(user_id).await.unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use rand::Rng;
+use std::sync::LazyLock;
+use subtle::ConstantTimeEq;
+
+// 用户不存在时用于校验的哑哈希，使两种失败的耗时一致
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| hash_password("isekai-dummy-password"));
+
+// 密码校验结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum PasswordCheck {
+    Valid,       // 密码正确
+    NeedsRehash, // 密码正确，但存储的是明文或旧参数的哈希，需要升级
+    Invalid,     // 密码错误
+}
+
+impl PasswordCheck {
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, PasswordCheck::Invalid)
+    }
+}
+
+// 使用 Argon2id 计算密码哈希，返回 PHC 格式字符串
+pub fn hash_password(password: &str) -> String {
+    let mut salt = [0u8; 16];
+    rand::rng().fill(&mut salt);
+    let salt = SaltString::encode_b64(&salt).expect("Failed to encode password salt");
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Failed to hash password")
+        .to_string()
+}
+
+// 在阻塞线程池中计算哈希，供异步上下文调用，避免 Argon2 阻塞运行时
+pub async fn hash_password_blocking(password: &str) -> String {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .expect("Password hashing task panicked")
+}
+
+// 校验密码，兼容尚未升级的明文记录
+pub fn verify_password(password: &str, stored: &str) -> PasswordCheck {
+    let Ok(parsed) = PasswordHash::new(stored) else {
+        // 无法解析为 PHC 字符串的记录视为旧版明文，使用常量时间比较
+        return if bool::from(password.as_bytes().ct_eq(stored.as_bytes())) {
+            PasswordCheck::NeedsRehash
+        } else {
+            PasswordCheck::Invalid
+        };
+    };
+
+    let argon2 = Argon2::default();
+    if argon2
+        .verify_password(password.as_bytes(), &parsed)
+        .is_err()
+    {
+        return PasswordCheck::Invalid;
+    }
+
+    // 算法或参数与当前默认值不同的哈希需要重新计算
+    let current = argon2::Params::default();
+    let outdated = parsed.algorithm != argon2::ARGON2ID_IDENT
+        || argon2::Params::try_from(&parsed).map_or(true, |params| {
+            params.m_cost() != current.m_cost()
+                || params.t_cost() != current.t_cost()
+                || params.p_cost() != current.p_cost()
+        });
+    if outdated {
+        PasswordCheck::NeedsRehash
+    } else {
+        PasswordCheck::Valid
+    }
+}
+
+// 对哑哈希做一次校验，用于用户不存在的分支
+pub fn verify_dummy(password: &str) {
+    let _ = verify_password(password, &DUMMY_HASH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_verify() {
+        let hash = hash_password("secret");
+        assert!(hash.starts_with("$argon2id$"));
+        assert_ne!(hash, hash_password("secret"));
+        assert_eq!(verify_password("secret", &hash), PasswordCheck::Valid);
+        assert_eq!(verify_password("wrong", &hash), PasswordCheck::Invalid);
+    }
+
+    #[test]
+    fn test_legacy_plaintext() {
+        assert_eq!(
+            verify_password("secret", "secret"),
+            PasswordCheck::NeedsRehash
+        );
+        assert_eq!(verify_password("wrong", "secret"), PasswordCheck::Invalid);
+    }
+}